@@ -0,0 +1,121 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : batch/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Non-interactive batch output for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    collections::HashSet,
+    io::{self, BufWriter, Write},
+};
+use anyhow::{bail, Result};
+use sysinfo::Pid;
+use tokio::sync::mpsc;
+use crate::{
+    cli::{Options, OutputFormat},
+    collector::{Collector, Snapshot, Update},
+    config::ConfigFile,
+    configured_process_list, export,
+    process::{format_row, format_timestamp, Column, MemoryKind, Process, SortField},
+    ui::{stats_line, Meter},
+};
+
+// How many times to print; None until interrupted
+fn iterations(options: &Options) -> Option<u64> {
+    options.iterations.or((!options.batch).then_some(1))
+}
+
+// Rejects options that would print something unreadable, before anything is started
+pub fn check(options: &Options) -> Result<()> {
+    // JSON documents cannot follow each other in one stream, NDJSON lines can
+    if options.output == Some(OutputFormat::Json) && iterations(options) != Some(1) {
+        bail!("--output json prints a single snapshot; use --output ndjson for more");
+    }
+    Ok(())
+}
+
+// Prints the summary and the process table, or a snapshot in the `--output` format, each time
+// the processes are read; `--iterations` times or until interrupted, once for a plain --output.
+// The first reading is skipped, as CPU usage needs two
+pub async fn run(
+    options: &Options,
+    config: ConfigFile,
+    collector: Collector,
+    mut updates: mpsc::UnboundedReceiver<Update>,
+) -> Result<()> {
+    for warning in &config.warnings {
+        tracing::warn!("config: {}", warning);
+        eprintln!("Config: {}", warning);
+    }
+    let columns = &config.config.columns;
+    let meters = &config.config.meters;
+    let mut process_list = configured_process_list(options, &config.config);
+    collector.memory_of_all(matches!(process_list.sort_field(), SortField::Memory(kind) if kind != MemoryKind::Rss));
+
+    // Every listed process is printed, so all of them count as visible
+    let mut visible = HashSet::new();
+    let mut readings = 0;
+    let mut printed = 0;
+    let iterations = iterations(options);
+    while iterations.is_none_or(|iterations| printed < iterations) {
+        let snapshot = match updates.recv().await {
+            Some(Update::Snapshot(snapshot)) => snapshot,
+            Some(Update::Killed(..)) => continue,
+            None => bail!("the collector thread stopped"),
+        };
+        process_list.apply(&snapshot.processes, &snapshot.threads);
+        let listed: HashSet<Pid> = process_list.processes().iter().map(|process| process.pid).collect();
+        if listed != visible {
+            collector.visible(listed.clone());
+            visible = listed;
+        }
+
+        if !snapshot.fresh_processes {
+            continue;
+        }
+        readings += 1;
+        if readings == 1 {
+            continue;
+        }
+        let result = match options.output {
+            Some(format) => print_as(format, &snapshot, process_list.processes(), columns, printed == 0),
+            None => print(&snapshot, process_list.processes(), columns, meters),
+        };
+        match result {
+            Ok(()) => printed += 1,
+            // The reader went away, as with `| head`
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => break,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+fn print(snapshot: &Snapshot, rows: &[Process], columns: &[Column], meters: &[Meter]) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    writeln!(out, "{}  {}", format_timestamp(snapshot.time), stats_line(meters, &snapshot.system))?;
+    let header: Vec<String> = columns.iter().map(|column| column.cell(column.header())).collect();
+    writeln!(out, "{}", header.join(" "))?;
+    for process in rows {
+        writeln!(out, "{}", format_row(process, columns))?;
+    }
+    writeln!(out)?;
+    out.flush()
+}
+
+fn print_as(format: OutputFormat, snapshot: &Snapshot, rows: &[Process], columns: &[Column], first: bool) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    export::write(&mut out, format, snapshot, rows, columns, first)?;
+    out.flush()
+}
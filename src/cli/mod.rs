@@ -0,0 +1,181 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : cli/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Command-line options for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::path::PathBuf;
+use clap::{ArgGroup, Parser, ValueEnum};
+use crate::{
+    config,
+    process::{MemoryKind, SortField, SortOrder},
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "blackwin-htop", version, about)]
+#[command(group(ArgGroup::new("noninteractive").args(["batch", "output"]).multiple(true)))]
+pub struct Options {
+    /// Refresh interval in milliseconds [default: 250]
+    #[arg(short, long, value_name = "MS", value_parser = clap::value_parser!(u64).range(config::MIN_DELAY..))]
+    pub delay: Option<u64>,
+
+    /// Initial sort column
+    #[arg(short, long, value_enum, value_name = "COLUMN")]
+    pub sort_key: Option<SortKey>,
+
+    /// Initial sort direction [default: natural direction of the column]
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub sort_order: Option<Order>,
+
+    /// Initial process name filter
+    #[arg(short = 'F', long, value_name = "TEXT")]
+    pub filter: Option<String>,
+
+    /// Only show the given process ids (comma separated or repeated)
+    #[arg(short, long = "pid", value_name = "PID", value_delimiter = ',')]
+    pub pids: Vec<u32>,
+
+    /// Only show processes of the given users, by name or numeric id
+    #[arg(short, long = "user", value_name = "USER", value_delimiter = ',')]
+    pub users: Vec<String>,
+
+    /// Start in tree view
+    #[arg(short, long)]
+    pub tree: bool,
+
+    /// Color theme [default: cyberpunk]
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+
+    /// Path of the configuration file [default: $XDG_CONFIG_HOME/blackwin-htop/config.toml]
+    #[arg(short, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Disable actions that change the system, such as killing processes
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Log file, rotated daily [default: $XDG_STATE_HOME/blackwin-htop/blackwin-htop.log]
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Log level or filter such as "debug" [default: $BLACKWIN_HTOP_LOG or info]
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Where system data is read from [default: procfs with --proc-root, otherwise sysinfo]
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    pub source: Option<SourceKind>,
+
+    /// Root of the proc filesystem for the procfs source, such as /host/proc in a container
+    #[arg(long, value_name = "PATH")]
+    pub proc_root: Option<PathBuf>,
+
+    /// Print the system summary and process table to stdout every delay instead of starting the interface
+    #[arg(short, long)]
+    pub batch: bool,
+
+    /// Print snapshots of the listed processes in a machine-readable format; implies --batch
+    #[arg(short, long, value_enum, value_name = "FORMAT")]
+    pub output: Option<OutputFormat>,
+
+    /// Number of tables to print in batch mode [default: until interrupted, 1 with only --output]
+    #[arg(short = 'n', long, value_name = "N", requires = "noninteractive", value_parser = clap::value_parser!(u64).range(1..))]
+    pub iterations: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SourceKind {
+    Sysinfo,
+    Procfs,
+    // Prepared data of a made-up machine
+    Demo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    // A single pretty-printed snapshot
+    Json,
+    // One row per process, after a header line of the column names
+    Csv,
+    // One document per line
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum SortKey {
+    Pid,
+    Name,
+    Cpu,
+    #[value(alias = "memory", alias = "mem")]
+    Rss,
+    Pss,
+    Uss,
+    Shared,
+    Swap,
+}
+
+impl From<SortField> for SortKey {
+    fn from(field: SortField) -> Self {
+        match field {
+            SortField::Pid => SortKey::Pid,
+            SortField::Name => SortKey::Name,
+            SortField::Cpu => SortKey::Cpu,
+            SortField::Memory(MemoryKind::Rss) => SortKey::Rss,
+            SortField::Memory(MemoryKind::Pss) => SortKey::Pss,
+            SortField::Memory(MemoryKind::Uss) => SortKey::Uss,
+            SortField::Memory(MemoryKind::Shared) => SortKey::Shared,
+            SortField::Memory(MemoryKind::Swap) => SortKey::Swap,
+        }
+    }
+}
+
+impl From<SortKey> for SortField {
+    fn from(key: SortKey) -> Self {
+        match key {
+            SortKey::Pid => SortField::Pid,
+            SortKey::Name => SortField::Name,
+            SortKey::Cpu => SortField::Cpu,
+            SortKey::Rss => SortField::Memory(MemoryKind::Rss),
+            SortKey::Pss => SortField::Memory(MemoryKind::Pss),
+            SortKey::Uss => SortField::Memory(MemoryKind::Uss),
+            SortKey::Shared => SortField::Memory(MemoryKind::Shared),
+            SortKey::Swap => SortField::Memory(MemoryKind::Swap),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+impl From<SortOrder> for Order {
+    fn from(order: SortOrder) -> Self {
+        match order {
+            SortOrder::Ascending => Order::Asc,
+            SortOrder::Descending => Order::Desc,
+        }
+    }
+}
+
+impl From<Order> for SortOrder {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Asc => SortOrder::Ascending,
+            Order::Desc => SortOrder::Descending,
+        }
+    }
+}
@@ -0,0 +1,621 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : collector/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Background data collection for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use anyhow::Result;
+use sysinfo::Pid;
+use tokio::sync::mpsc as tokio_mpsc;
+use serde::{Deserialize, Serialize};
+use crate::{
+    ids,
+    process::{Process, ProcessDetail, ProcessKind},
+    procfs::{net::Connection, FileDescriptor, Mapping, Stat},
+    system::SystemInfo,
+};
+
+mod procfs_source;
+mod scripted;
+mod source;
+mod sysinfo_source;
+
+pub use procfs_source::ProcfsSource;
+pub use scripted::{Frame, ScriptedSource};
+pub use source::{ProcessSample, Source};
+pub use sysinfo_source::SysinfoSource;
+
+// Reading smaps_rollup walks every mapping of a process, so it runs far less often than the CPU tick
+pub const DEFAULT_DETAILS_INTERVAL: u64 = 2000;
+
+// Refresh intervals of the data sources in milliseconds; those left unset follow the delay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Intervals {
+    // CPU and memory meters
+    pub cpu: Option<u64>,
+    pub processes: Option<u64>,
+    // smaps memory of each process
+    pub details: u64,
+    // Detail, files, memory map and network views
+    pub views: Option<u64>,
+}
+
+impl Default for Intervals {
+    fn default() -> Self {
+        Self {
+            cpu: None,
+            processes: None,
+            details: DEFAULT_DETAILS_INTERVAL,
+            views: None,
+        }
+    }
+}
+
+// Data collected besides the processes, for the view being shown
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Watched {
+    #[default]
+    None,
+    Detail(Pid),
+    Files(Pid),
+    MemoryMap(Pid),
+    Network,
+}
+
+// Data of the secondary view being shown, tagged with the process it belongs to
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub enum ViewData {
+    #[default]
+    None,
+    Detail(#[serde(deserialize_with = "ids::id")] Pid, Option<Box<ProcessDetail>>),
+    Files(#[serde(deserialize_with = "ids::id")] Pid, Result<Vec<FileDescriptor>, String>),
+    MemoryMap(#[serde(deserialize_with = "ids::id")] Pid, Result<Vec<Mapping>, String>),
+    Network(Vec<Connection>),
+}
+
+// Time spent on each part of one collection; sources that were not due take none
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Timings {
+    pub system: Duration,
+    pub processes: Duration,
+    pub memory: Duration,
+    pub view: Duration,
+    pub total: Duration,
+}
+
+// The latest data of every source, published whenever one of them was read; never changed
+// once published, and the parts that were not read again are shared with the previous one
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub system: SystemInfo,
+    // Processes and kernel threads; user-land threads are kept apart, by process
+    pub processes: Arc<Vec<Process>>,
+    #[serde(deserialize_with = "ids::id_map")]
+    pub threads: Arc<HashMap<Pid, Vec<Process>>>,
+    // Whether the process source was read for this snapshot; otherwise only their memory may be newer
+    pub fresh_processes: bool,
+    pub view: Arc<ViewData>,
+    // Processes that appeared and disappeared since the previous collection
+    pub started: usize,
+    pub exited: usize,
+    // Seconds since the epoch
+    pub time: u64,
+    pub timings: Timings,
+    pub interval: Duration,
+}
+
+pub enum Update {
+    Snapshot(Arc<Snapshot>),
+    Killed(Pid, Result<(), String>),
+}
+
+enum Command {
+    ShowThreads(bool),
+    Watch(Watched),
+    SetDelay(Duration),
+    Visible(HashSet<Pid>),
+    MemoryOfAll(bool),
+    Kill(Pid),
+}
+
+// Handle of the collector thread; dropping it stops the thread after its current collection
+pub struct Collector {
+    commands: mpsc::Sender<Command>,
+    delay: Duration,
+}
+
+impl Collector {
+    // Collects from `source` right away, then each kind of data on its own interval; those
+    // without one every `delay`
+    pub fn spawn(
+        source: Box<dyn Source>,
+        delay: Duration,
+        intervals: Intervals,
+        show_threads: bool,
+    ) -> Result<(Self, tokio_mpsc::UnboundedReceiver<Update>)> {
+        let (commands, receiver) = mpsc::channel();
+        let (updates, updates_rx) = tokio_mpsc::unbounded_channel();
+        thread::Builder::new()
+            .name("collector".to_string())
+            .spawn(move || Worker::new(source, delay, intervals, show_threads).run(receiver, updates))?;
+        Ok((Self { commands, delay }, updates_rx))
+    }
+
+    // A send only fails when the thread is gone, which the event loop reports on its own
+    fn send(&self, command: Command) {
+        let _ = self.commands.send(command);
+    }
+
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    pub fn set_delay(&mut self, delay: Duration) {
+        self.delay = delay;
+        self.send(Command::SetDelay(delay));
+    }
+
+    pub fn show_threads(&self, show: bool) {
+        self.send(Command::ShowThreads(show));
+    }
+
+    // Also collects `watched` from now on
+    pub fn watch(&self, watched: Watched) {
+        self.send(Command::Watch(watched));
+    }
+
+    // The processes on screen, whose costlier fields are kept current
+    pub fn visible(&self, pids: HashSet<Pid>) {
+        self.send(Command::Visible(pids));
+    }
+
+    // Whether smaps_rollup is read for every process rather than only the visible ones,
+    // needed while sorting by a column only it provides
+    pub fn memory_of_all(&self, all: bool) {
+        self.send(Command::MemoryOfAll(all));
+    }
+
+    pub fn kill(&self, pid: Pid) {
+        self.send(Command::Kill(pid));
+    }
+}
+
+// When each source was last read; None reads it at the next wake-up
+#[derive(Default)]
+struct Refreshed {
+    cpu: Option<Instant>,
+    processes: Option<Instant>,
+    details: Option<Instant>,
+    view: Option<Instant>,
+}
+
+struct Worker {
+    source: Box<dyn Source>,
+    delay: Duration,
+    intervals: Intervals,
+    refreshed: Refreshed,
+    show_threads: bool,
+    watched: Watched,
+    // Every listed process by pid, updated in place so that only new processes are read in full
+    table: HashMap<Pid, Process>,
+    visible: HashSet<Pid>,
+    memory_of_all: bool,
+    // What was published last, reused for the sources that are not due
+    latest: Arc<Snapshot>,
+}
+
+impl Worker {
+    fn new(source: Box<dyn Source>, delay: Duration, intervals: Intervals, show_threads: bool) -> Self {
+        Self {
+            source,
+            delay,
+            intervals,
+            refreshed: Refreshed::default(),
+            show_threads,
+            watched: Watched::None,
+            table: HashMap::new(),
+            visible: HashSet::new(),
+            memory_of_all: false,
+            latest: Arc::default(),
+        }
+    }
+
+    fn run(mut self, commands: mpsc::Receiver<Command>, updates: tokio_mpsc::UnboundedSender<Update>) {
+        loop {
+            let snapshot = Arc::new(self.collect());
+            self.latest = Arc::clone(&snapshot);
+            if updates.send(Update::Snapshot(snapshot)).is_err() {
+                return;
+            }
+
+            // Commands are handled while waiting; those changing what is shown make their source due at once
+            loop {
+                let deadline = self.next_due();
+                match commands.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Ok(Command::ShowThreads(show)) => {
+                        self.show_threads = show;
+                        self.refreshed.processes = None;
+                    }
+                    Ok(Command::Watch(watched)) => {
+                        self.watched = watched;
+                        self.refreshed.view = None;
+                    }
+                    Ok(Command::SetDelay(delay)) => self.delay = delay,
+                    Ok(Command::Visible(pids)) => self.visible = pids,
+                    Ok(Command::MemoryOfAll(all)) => {
+                        if all && !self.memory_of_all {
+                            self.refreshed.details = None;
+                        }
+                        self.memory_of_all = all;
+                    }
+                    Ok(Command::Kill(pid)) => {
+                        let result = self.source.kill(pid).map_err(|err| format!("{:#}", err));
+                        if updates.send(Update::Killed(pid, result)).is_err() {
+                            return;
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        }
+    }
+
+    fn interval(&self, interval: Option<u64>) -> Duration {
+        interval.map_or(self.delay, Duration::from_millis)
+    }
+
+    // When each source was last read and how often it is; None for a source never read
+    fn sources(&self) -> [(Option<Instant>, Option<Duration>); 4] {
+        let view = match self.watched {
+            Watched::None => None,
+            _ => Some(self.interval(self.intervals.views)),
+        };
+        [
+            (self.refreshed.cpu, Some(self.interval(self.intervals.cpu))),
+            (self.refreshed.processes, Some(self.interval(self.intervals.processes))),
+            (self.refreshed.details, Some(Duration::from_millis(self.intervals.details))),
+            (self.refreshed.view, view),
+        ]
+    }
+
+    fn next_due(&self) -> Instant {
+        let now = Instant::now();
+        self.sources()
+            .into_iter()
+            .filter_map(|(last, interval)| interval.map(|interval| last.map_or(now, |at| at + interval)))
+            .min()
+            .unwrap_or(now)
+    }
+
+    // Reads the sources that are due and takes the others from the previous snapshot
+    fn collect(&mut self) -> Snapshot {
+        let started = Instant::now();
+        let [cpu, processes, details, view] = self
+            .sources()
+            .map(|(last, interval)| {
+                interval.is_some_and(|interval| last.is_none_or(|at| started.saturating_duration_since(at) >= interval))
+            });
+        let mut timings = Timings::default();
+
+        let system = if cpu {
+            self.refreshed.cpu = Some(started);
+            self.source.system()
+        } else {
+            self.latest.system.clone()
+        };
+        let system_done = Instant::now();
+        timings.system = system_done - started;
+
+        let (new_pids, exited) = if processes {
+            self.source.refresh_processes(self.show_threads);
+            self.refreshed.processes = Some(started);
+            self.update_table()
+        } else {
+            (Vec::new(), 0)
+        };
+        let processes_done = Instant::now();
+        timings.processes = processes_done - system_done;
+
+        if details {
+            self.refreshed.details = Some(started);
+        }
+        self.update_memory(&new_pids, details);
+        let memory_done = Instant::now();
+        timings.memory = memory_done - processes_done;
+
+        let (processes, threads) = if processes || details {
+            let mut processes = Vec::with_capacity(self.table.len());
+            let mut threads: HashMap<Pid, Vec<Process>> = HashMap::new();
+            for process in self.table.values() {
+                match (process.kind, process.parent) {
+                    (ProcessKind::Thread, Some(parent)) => threads.entry(parent).or_default().push(process.clone()),
+                    (ProcessKind::Thread, None) => {}
+                    _ => processes.push(process.clone()),
+                }
+            }
+            (Arc::new(processes), Arc::new(threads))
+        } else {
+            (Arc::clone(&self.latest.processes), Arc::clone(&self.latest.threads))
+        };
+
+        let view = if view {
+            self.refreshed.view = Some(started);
+            Arc::new(self.collect_view())
+        } else {
+            Arc::clone(&self.latest.view)
+        };
+        let done = Instant::now();
+        timings.view = done - memory_done;
+        timings.total = done - started;
+
+        Snapshot {
+            system,
+            processes,
+            threads,
+            fresh_processes: self.refreshed.processes == Some(started),
+            view,
+            started: new_pids.len(),
+            exited,
+            time: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            timings,
+            interval: self.delay,
+        }
+    }
+
+    // Brings the table in line with the refreshed source; returns the new pids and how many left.
+    // Only new processes are read in full, the others just take the fields of the sample,
+    // plus their stat line while visible
+    fn update_table(&mut self) -> (Vec<Pid>, usize) {
+        let source = &self.source;
+        let samples = source.processes(self.show_threads);
+        let alive: HashSet<Pid> = samples.iter().map(|sample| sample.pid).collect();
+        let before = self.table.len();
+        self.table.retain(|pid, _| alive.contains(pid));
+        let mut exited = before - self.table.len();
+
+        let mut started = Vec::new();
+        for sample in &samples {
+            match self.table.entry(sample.pid) {
+                Entry::Occupied(mut entry) if entry.get().start_time == sample.start_time => {
+                    let stat = if self.visible.contains(&sample.pid) { source.stat(sample.pid) } else { None };
+                    refresh_process(entry.get_mut(), sample, stat);
+                }
+                // The pid was reused: the process that had it exited and a new one started
+                Entry::Occupied(mut entry) => {
+                    let user = sample.uid.as_ref().and_then(|uid| source.user_name(uid));
+                    entry.insert(new_process(sample, user, source.stat(sample.pid)));
+                    started.push(sample.pid);
+                    exited += 1;
+                }
+                Entry::Vacant(entry) => {
+                    let user = sample.uid.as_ref().and_then(|uid| source.user_name(uid));
+                    entry.insert(new_process(sample, user, source.stat(sample.pid)));
+                    started.push(sample.pid);
+                }
+            }
+        }
+        (started, exited)
+    }
+
+    // New processes are read at once; when `due`, the visible ones too, or all of them
+    // while sorting by smaps-only memory
+    fn update_memory(&mut self, started: &[Pid], due: bool) {
+        let refreshed: Vec<Pid> = match (due, self.memory_of_all) {
+            (false, _) => Vec::new(),
+            (true, true) => self.table.keys().copied().collect(),
+            (true, false) => self.visible.iter().copied().collect(),
+        };
+
+        for pid in started.iter().chain(&refreshed) {
+            if let Some(process) = self.table.get_mut(pid).filter(|process| process.kind == ProcessKind::Process) {
+                process.memory = self.source.memory(*pid);
+            }
+        }
+    }
+
+    fn collect_view(&mut self) -> ViewData {
+        match self.watched {
+            Watched::None => ViewData::None,
+            Watched::Detail(pid) => ViewData::Detail(pid, self.source.detail(pid).map(Box::new)),
+            Watched::Files(pid) => ViewData::Files(pid, self.source.files(pid)),
+            Watched::MemoryMap(pid) => ViewData::MemoryMap(pid, self.source.memory_map(pid)),
+            Watched::Network => ViewData::Network(self.source.connections()),
+        }
+    }
+}
+
+// A process seen for the first time
+fn new_process(sample: &ProcessSample, user: Option<String>, stat: Option<Stat>) -> Process {
+    Process {
+        pid: sample.pid,
+        parent: sample.parent,
+        uid: sample.uid.clone(),
+        user,
+        name: sample.name.to_string(),
+        cpu_usage: sample.cpu_usage,
+        memory_usage: sample.memory_usage,
+        memory: None,
+        state: stat.as_ref().map_or(sample.state, |stat| stat.state),
+        last_cpu: stat.map(|stat| stat.processor),
+        kind: sample.kind,
+        start_time: sample.start_time,
+        depth: 0,
+    }
+}
+
+// Updates what changes between ticks in place; `stat` is only read again for visible processes
+fn refresh_process(process: &mut Process, sample: &ProcessSample, stat: Option<Stat>) {
+    process.parent = sample.parent;
+    process.cpu_usage = sample.cpu_usage;
+    process.memory_usage = sample.memory_usage;
+    if process.name != sample.name {
+        process.name = sample.name.to_string();
+    }
+    match stat {
+        Some(stat) => {
+            process.state = stat.state;
+            process.last_cpu = Some(stat.processor);
+        }
+        None => process.state = sample.state,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::ProcessList;
+
+    const ROOT: (u32, &str) = (0, "root");
+    const DEMO: (u32, &str) = (1000, "demo");
+
+    fn process(pid: u32, cpu_usage: f32, user: (u32, &str), start_time: u64) -> Process {
+        Process {
+            pid: Pid::from_u32(pid),
+            parent: None,
+            uid: user.0.to_string().parse().ok(),
+            user: Some(user.1.to_string()),
+            name: format!("task{}", pid),
+            cpu_usage,
+            memory_usage: 1024,
+            memory: None,
+            state: 'S',
+            last_cpu: Some(0),
+            kind: ProcessKind::Process,
+            start_time,
+            depth: 0,
+        }
+    }
+
+    fn frame(processes: Vec<Process>) -> Frame {
+        Frame { system: SystemInfo::default(), processes }
+    }
+
+    // Every source is due at each collection
+    fn worker(frames: Vec<Frame>) -> Worker {
+        let intervals = Intervals { details: 0, ..Intervals::default() };
+        Worker::new(Box::new(ScriptedSource::new(frames)), Duration::ZERO, intervals, false)
+    }
+
+    // Collects and publishes like the collector thread does
+    fn collect(worker: &mut Worker) -> Arc<Snapshot> {
+        let snapshot = Arc::new(worker.collect());
+        worker.latest = Arc::clone(&snapshot);
+        snapshot
+    }
+
+    fn pids(processes: &[Process]) -> Vec<u32> {
+        processes.iter().map(|process| process.pid.as_u32()).collect()
+    }
+
+    #[test]
+    fn counts_started_and_exited() {
+        let mut worker = worker(vec![
+            frame(vec![process(1, 0.0, ROOT, 1), process(2, 0.0, ROOT, 1), process(3, 0.0, ROOT, 1)]),
+            frame(vec![process(1, 0.0, ROOT, 1), process(3, 0.0, ROOT, 1), process(4, 0.0, ROOT, 1)]),
+            // Pid 4 now belongs to another process
+            frame(vec![process(1, 0.0, ROOT, 1), process(3, 0.0, ROOT, 1), process(4, 0.0, DEMO, 2)]),
+        ]);
+
+        let snapshot = collect(&mut worker);
+        assert_eq!((snapshot.started, snapshot.exited), (3, 0));
+        let snapshot = collect(&mut worker);
+        assert_eq!((snapshot.started, snapshot.exited), (1, 1));
+        let snapshot = collect(&mut worker);
+        assert_eq!((snapshot.started, snapshot.exited), (1, 1));
+        let reused = snapshot.processes.iter().find(|process| process.pid.as_u32() == 4).unwrap();
+        assert_eq!(reused.user.as_deref(), Some("demo"));
+    }
+
+    #[test]
+    fn resorts_when_usage_changes() {
+        let mut worker = worker(vec![
+            frame(vec![process(1, 10.0, ROOT, 1), process(2, 50.0, ROOT, 1)]),
+            frame(vec![process(1, 80.0, ROOT, 1), process(2, 50.0, ROOT, 1)]),
+        ]);
+        let mut list = ProcessList::new();
+
+        let snapshot = collect(&mut worker);
+        assert!(list.apply(&snapshot.processes, &snapshot.threads));
+        assert_eq!(pids(list.processes()), [2, 1]);
+        let snapshot = collect(&mut worker);
+        assert!(list.apply(&snapshot.processes, &snapshot.threads));
+        assert_eq!(pids(list.processes()), [1, 2]);
+        // The frames start over with the same usage as the first
+        let snapshot = collect(&mut worker);
+        assert!(list.apply(&snapshot.processes, &snapshot.threads));
+        assert_eq!(pids(list.processes()), [2, 1]);
+    }
+
+    #[test]
+    fn killed_processes_leave_later_frames() {
+        let processes = vec![process(1, 0.0, ROOT, 1), process(2, 0.0, ROOT, 1)];
+        let mut worker = worker(vec![frame(processes.clone()), frame(processes)]);
+
+        collect(&mut worker);
+        assert!(worker.source.kill(Pid::from_u32(2)).is_ok());
+        let snapshot = collect(&mut worker);
+        assert_eq!(pids(&snapshot.processes), [1]);
+        assert_eq!((snapshot.started, snapshot.exited), (0, 1));
+        assert!(collect(&mut worker).processes.iter().all(|process| process.pid.as_u32() != 2));
+        assert!(worker.source.kill(Pid::from_u32(2)).is_err());
+    }
+
+    #[test]
+    fn reuses_sources_not_due() {
+        let intervals = Intervals { cpu: Some(60_000), processes: Some(60_000), details: 60_000, views: None };
+        let source = ScriptedSource::new(vec![
+            frame(vec![process(1, 0.0, ROOT, 1)]),
+            frame(vec![process(2, 0.0, ROOT, 1)]),
+        ]);
+        let mut worker = Worker::new(Box::new(source), Duration::from_secs(60), intervals, false);
+
+        let first = collect(&mut worker);
+        assert!(first.fresh_processes);
+        let second = collect(&mut worker);
+        assert!(!second.fresh_processes);
+        assert!(Arc::ptr_eq(&first.processes, &second.processes));
+        assert!(Arc::ptr_eq(&first.threads, &second.threads));
+        assert!(Arc::ptr_eq(&first.view, &second.view));
+        assert_eq!((second.started, second.exited), (0, 0));
+
+        // Once due again, the processes are read from the next frame
+        worker.refreshed.processes = None;
+        let third = collect(&mut worker);
+        assert!(third.fresh_processes);
+        assert_eq!(pids(&third.processes), [2]);
+        assert_eq!((third.started, third.exited), (1, 1));
+    }
+
+    #[test]
+    fn snapshots_read_back() {
+        let mut worker = Worker::new(Box::new(ScriptedSource::demo()), Duration::ZERO, Intervals::default(), true);
+        worker.watched = Watched::Detail(Pid::from_u32(1501));
+        let snapshot = collect(&mut worker);
+
+        let json = serde_json::to_string(&*snapshot).unwrap();
+        let read: Snapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.system, snapshot.system);
+        assert_eq!(read.processes, snapshot.processes);
+        assert_eq!(read.threads, snapshot.threads);
+        assert!(matches!(&*read.view, ViewData::Detail(pid, Some(detail)) if pid.as_u32() == 1501 && detail.name == "cargo"));
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+    }
+}
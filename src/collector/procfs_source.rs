@@ -0,0 +1,421 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : collector/procfs_source.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Direct /proc data source for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use anyhow::{bail, Result};
+use sysinfo::{Pid, Uid, Users};
+use crate::{
+    process::{ProcessDetail, ProcessKind},
+    procfs::{self, net::Connection, FileDescriptor, Mapping, MemoryBreakdown, Stat, PROC_ROOT},
+    system::{Core, SystemInfo},
+};
+use super::{ProcessSample, Source};
+
+// PF_KTHREAD of the per-process flags
+const KERNEL_THREAD_FLAG: u32 = 0x0020_0000;
+
+// Reads a proc filesystem itself rather than through sysinfo, so it can watch the host from
+// a container that mounts its /proc elsewhere
+pub struct ProcfsSource {
+    root: PathBuf,
+    users: Users,
+    clock_ticks: u64,
+    page_size: u64,
+    // Busy and total time of each CPU, the first entry being all of them, at the last system()
+    cpu_times: Vec<(u64, u64)>,
+    // Total time of all CPUs at the last process refresh
+    total_time: u64,
+    cpu_count: usize,
+    entries: HashMap<Pid, Entry>,
+}
+
+struct Entry {
+    name: String,
+    parent: Option<Pid>,
+    uid: Option<Uid>,
+    state: char,
+    kind: ProcessKind,
+    // Tells a reused pid from the process that had it before
+    start_time: u64,
+    cpu_time: u64,
+    cpu_usage: f32,
+    memory_usage: u64,
+}
+
+impl ProcfsSource {
+    // `root` is where the proc filesystem is mounted, procfs::PROC_ROOT for this machine's
+    pub fn new(root: PathBuf) -> Self {
+        let (clock_ticks, page_size) = system_constants();
+        let mut source = Self {
+            root,
+            users: Users::new_with_refreshed_list(),
+            clock_ticks,
+            page_size,
+            cpu_times: Vec::new(),
+            total_time: 0,
+            cpu_count: 1,
+            entries: HashMap::new(),
+        };
+        source.cpu_times = source.read_cpu_times();
+        source.cpu_count = source.cpu_times.len().saturating_sub(1).max(1);
+        source
+    }
+
+    fn dir(&self, pid: Pid) -> PathBuf {
+        procfs::pid_path(&self.root, pid.as_u32())
+    }
+
+    // Busy and total time of the "cpu" and "cpuN" lines of /proc/stat
+    fn read_cpu_times(&self) -> Vec<(u64, u64)> {
+        let content = fs::read_to_string(self.root.join("stat")).unwrap_or_default();
+        content
+            .lines()
+            .filter(|line| line.starts_with("cpu"))
+            .map(|line| {
+                let times: Vec<u64> = line.split_whitespace().skip(1).map(|n| n.parse().unwrap_or(0)).collect();
+                let total: u64 = times.iter().sum();
+                // idle and iowait
+                let idle = times.get(3).copied().unwrap_or(0) + times.get(4).copied().unwrap_or(0);
+                (total - idle, total)
+            })
+            .collect()
+    }
+
+    fn read_entry(&self, pid: Pid, dir: &Path, thread_of: Option<Pid>, elapsed: u64) -> Option<Entry> {
+        let stat = procfs::read_stat(dir)?;
+        let previous = self.entries.get(&pid).filter(|entry| entry.start_time == stat.start_time);
+        let cpu_usage = match previous {
+            Some(previous) if elapsed > 0 => {
+                stat.cpu_time.saturating_sub(previous.cpu_time) as f32 / elapsed as f32 * 100.0 * self.cpu_count as f32
+            }
+            _ => 0.0,
+        };
+        // The owner never changes short of a setuid, so status is only read for new processes
+        let uid = match previous {
+            Some(previous) => previous.uid.clone(),
+            None => procfs::read_credentials(dir).and_then(|credentials| uid(credentials.uid[0])),
+        };
+        let kind = match thread_of {
+            Some(_) => ProcessKind::Thread,
+            None if stat.flags & KERNEL_THREAD_FLAG != 0 => ProcessKind::KernelThread,
+            None => ProcessKind::Process,
+        };
+
+        Some(Entry {
+            parent: thread_of.or_else(|| (stat.parent != 0).then(|| Pid::from_u32(stat.parent))),
+            uid,
+            state: stat.state,
+            kind,
+            start_time: stat.start_time,
+            cpu_time: stat.cpu_time,
+            cpu_usage,
+            memory_usage: stat.rss * self.page_size,
+            name: stat.name,
+        })
+    }
+
+    // Seconds since the epoch at which the machine booted
+    fn boot_time(&self) -> u64 {
+        let content = fs::read_to_string(self.root.join("stat")).unwrap_or_default();
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|time| time.trim().parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+impl Source for ProcfsSource {
+    fn system(&mut self) -> SystemInfo {
+        let times = self.read_cpu_times();
+        let usage: Vec<f32> = times
+            .iter()
+            .enumerate()
+            .map(|(i, (busy, total))| {
+                let (previous_busy, previous_total) = self.cpu_times.get(i).copied().unwrap_or_default();
+                let elapsed = total.saturating_sub(previous_total);
+                if elapsed == 0 {
+                    0.0
+                } else {
+                    // iowait, counted in the total, can go down between reads (proc(5))
+                    (busy.saturating_sub(previous_busy) as f32 / elapsed as f32 * 100.0).clamp(0.0, 100.0)
+                }
+            })
+            .collect();
+        self.cpu_times = times;
+
+        let cores = usage.iter().skip(1).enumerate().map(|(i, usage)| Core::new(i, *usage)).collect();
+        SystemInfo::new(cores, usage.first().copied().unwrap_or(0.0), read_memory(&self.root))
+    }
+
+    fn refresh_processes(&mut self, threads: bool) {
+        let total_time = self.read_cpu_times().first().map_or(0, |(_, total)| *total);
+        let elapsed = total_time.saturating_sub(self.total_time);
+
+        let mut entries = HashMap::with_capacity(self.entries.len());
+        let Ok(dirs) = fs::read_dir(&self.root) else {
+            return;
+        };
+        for dir in dirs.flatten() {
+            let Some(pid) = pid_of(&dir) else {
+                continue;
+            };
+            let path = dir.path();
+            let Some(entry) = self.read_entry(pid, &path, None, elapsed) else {
+                continue;
+            };
+            let kernel = entry.kind == ProcessKind::KernelThread;
+            entries.insert(pid, entry);
+
+            if threads && !kernel {
+                for task in fs::read_dir(path.join("task")).into_iter().flatten().flatten() {
+                    let Some(tid) = pid_of(&task).filter(|tid| *tid != pid) else {
+                        continue;
+                    };
+                    if let Some(entry) = self.read_entry(tid, &task.path(), Some(pid), elapsed) {
+                        entries.insert(tid, entry);
+                    }
+                }
+            }
+        }
+        self.entries = entries;
+        self.total_time = total_time;
+    }
+
+    fn processes(&self, threads: bool) -> Vec<ProcessSample<'_>> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| threads || entry.kind != ProcessKind::Thread)
+            .map(|(pid, entry)| ProcessSample {
+                pid: *pid,
+                parent: entry.parent,
+                uid: entry.uid.clone(),
+                name: &entry.name,
+                cpu_usage: entry.cpu_usage,
+                memory_usage: entry.memory_usage,
+                state: entry.state,
+                kind: entry.kind,
+                start_time: entry.start_time,
+            })
+            .collect()
+    }
+
+    fn user_name(&self, uid: &Uid) -> Option<String> {
+        self.users.get_user_by_id(uid).map(|user| user.name().to_string())
+    }
+
+    fn detail(&mut self, pid: Pid) -> Option<ProcessDetail> {
+        let dir = self.dir(pid);
+        let stat = procfs::read_stat(&dir)?;
+
+        // Walk up the parent chain, guarding against cycles from pid reuse
+        let mut parents = Vec::new();
+        let mut seen = HashSet::from([pid]);
+        let mut current = stat.parent;
+        while current != 0 && seen.insert(Pid::from_u32(current)) {
+            let Some(parent) = procfs::read_stat(&procfs::pid_path(&self.root, current)) else {
+                break;
+            };
+            parents.push((Pid::from_u32(current), parent.name.clone()));
+            current = parent.parent;
+        }
+
+        let credentials = procfs::read_credentials(&dir);
+        let start_time = self.boot_time() + stat.start_time / self.clock_ticks.max(1);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+        let link = |name: &str| fs::read_link(dir.join(name)).ok();
+
+        Some(ProcessDetail {
+            pid,
+            name: stat.name.clone(),
+            exe: link("exe"),
+            cwd: link("cwd"),
+            root: link("root"),
+            cmdline: read_nul_separated(&dir.join("cmdline")),
+            environ: read_nul_separated(&dir.join("environ")),
+            user: credentials.and_then(|credentials| uid(credentials.uid[0])).and_then(|uid| self.user_name(&uid)),
+            credentials,
+            parents,
+            start_time,
+            run_time: now.saturating_sub(start_time),
+            state: state_name(stat.state).to_string(),
+            priority: Some(stat.priority),
+            nice: Some(stat.nice),
+            cgroups: procfs::read_cgroups(&dir),
+            namespaces: procfs::read_namespaces(&dir),
+            limits: procfs::read_limits(&dir),
+        })
+    }
+
+    // Pids below another root belong to another pid namespace, where the same number
+    // may well be a different process
+    fn kill(&mut self, pid: Pid) -> Result<()> {
+        if self.root != Path::new(PROC_ROOT) {
+            bail!("processes under {} cannot be killed from here", self.root.display());
+        }
+        send_kill(pid)
+    }
+
+    fn stat(&self, pid: Pid) -> Option<Stat> {
+        procfs::read_stat(&self.dir(pid))
+    }
+
+    fn memory(&self, pid: Pid) -> Option<MemoryBreakdown> {
+        procfs::read_smaps_rollup(&self.dir(pid))
+    }
+
+    fn files(&self, pid: Pid) -> Result<Vec<FileDescriptor>, String> {
+        let dir = self.dir(pid);
+        procfs::read_fds(&dir).map_err(|err| format!("Cannot read {}/fd: {}", dir.display(), err))
+    }
+
+    fn memory_map(&self, pid: Pid) -> Result<Vec<Mapping>, String> {
+        let dir = self.dir(pid);
+        procfs::read_smaps(&dir).map_err(|err| format!("Cannot read {}/smaps: {}", dir.display(), err))
+    }
+
+    fn connections(&self) -> Vec<Connection> {
+        procfs::net::read_connections(&self.root)
+    }
+}
+
+// Numbered entries of /proc and /proc/<pid>/task
+fn pid_of(entry: &fs::DirEntry) -> Option<Pid> {
+    entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()).map(Pid::from_u32)
+}
+
+fn uid(id: u32) -> Option<Uid> {
+    id.to_string().parse().ok()
+}
+
+// Used and total memory in bytes, used being what is not available, as sysinfo counts it
+fn read_memory(root: &Path) -> (u64, u64) {
+    let content = fs::read_to_string(root.join("meminfo")).unwrap_or_default();
+    let field = |name: &str| {
+        content
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map_or(0, |kb| kb * 1024)
+    };
+    let total = field("MemTotal:");
+    (total.saturating_sub(field("MemAvailable:")), total)
+}
+
+fn read_nul_separated(path: &Path) -> Vec<String> {
+    fs::read(path)
+        .map(|content| {
+            content
+                .split(|byte| *byte == 0)
+                .filter(|part| !part.is_empty())
+                .map(|part| String::from_utf8_lossy(part).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Names of the single-letter states of proc(5)
+fn state_name(state: char) -> &'static str {
+    match state {
+        'R' => "Runnable",
+        'S' => "Sleeping",
+        'D' => "UninterruptibleDiskSleep",
+        'Z' => "Zombie",
+        'T' => "Stopped",
+        't' => "Tracing",
+        'X' | 'x' => "Dead",
+        'K' => "Wakekill",
+        'W' => "Waking",
+        'P' => "Parked",
+        'I' => "Idle",
+        _ => "Unknown",
+    }
+}
+
+// Clock ticks per second and the page size
+#[cfg(unix)]
+fn system_constants() -> (u64, u64) {
+    // SAFETY: sysconf only reads configuration values
+    let (ticks, page) = unsafe { (libc::sysconf(libc::_SC_CLK_TCK), libc::sysconf(libc::_SC_PAGESIZE)) };
+    (u64::try_from(ticks).unwrap_or(100), u64::try_from(page).unwrap_or(4096))
+}
+
+#[cfg(not(unix))]
+fn system_constants() -> (u64, u64) {
+    (100, 4096)
+}
+
+#[cfg(unix)]
+fn send_kill(pid: Pid) -> Result<()> {
+    let pid = libc::pid_t::try_from(pid.as_u32())?;
+    // SAFETY: kill only takes plain integers
+    if unsafe { libc::kill(pid, libc::SIGKILL) } != 0 {
+        bail!("{}", std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_kill(_pid: Pid) -> Result<()> {
+    bail!("killing is not supported by the procfs source on this platform")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A machine with one process, as mounted at `root`
+    fn fake_proc(root: &Path, pid: u32, name: &str) {
+        let dir = root.join(pid.to_string());
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(root.join("stat"), "cpu  100 0 100 800 0 0 0 0 0 0\ncpu0 100 0 100 800 0 0 0 0 0 0\nbtime 1700000000\n").unwrap();
+        fs::write(root.join("meminfo"), "MemTotal:       2048 kB\nMemAvailable:   1024 kB\n").unwrap();
+        fs::write(dir.join("stat"), format!("{} ({}) S 1 {} {} 0 -1 4194560 0 0 0 0 5 3 0 0 20 0 1 0 1234 0 25", pid, name, pid, pid)).unwrap();
+        fs::write(dir.join("status"), "Name:\tfake\nUid:\t1000\t1000\t1000\t1000\nGid:\t1000\t1000\t1000\t1000\n").unwrap();
+    }
+
+    #[test]
+    fn sources_keep_their_own_root() {
+        let base = std::env::temp_dir().join(format!("blackwin-htop-procfs-{}", std::process::id()));
+        let (first, second) = (base.join("first"), base.join("second"));
+        fake_proc(&first, 42, "fake daemon");
+        fake_proc(&second, 7, "other");
+
+        let mut sources = [ProcfsSource::new(first.clone()), ProcfsSource::new(second)];
+        let seen: Vec<Vec<(u32, String)>> = sources
+            .iter_mut()
+            .map(|source| {
+                source.refresh_processes(false);
+                source.processes(false).iter().map(|sample| (sample.pid.as_u32(), sample.name.to_string())).collect()
+            })
+            .collect();
+        let system = sources[0].system();
+        let stat = sources[0].stat(Pid::from_u32(42));
+        let killed = sources[0].kill(Pid::from_u32(42));
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(seen, [vec![(42, "fake daemon".to_string())], vec![(7, "other".to_string())]]);
+        assert_eq!(system.memory_usage(), (1024 * 1024, 2048 * 1024));
+        assert_eq!(stat.map(|stat| stat.parent), Some(1));
+        assert!(killed.is_err());
+    }
+}
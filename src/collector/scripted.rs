@@ -0,0 +1,244 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : collector/scripted.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Scripted in-memory data source for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::collections::HashSet;
+use anyhow::{bail, Result};
+use sysinfo::{Pid, Uid};
+use serde::{Deserialize, Serialize};
+use crate::{
+    process::{Process, ProcessDetail, ProcessKind},
+    procfs::{net::Connection, FileDescriptor, Mapping, MemoryBreakdown, Stat},
+    system::{Core, SystemInfo},
+};
+use super::{ProcessSample, Source};
+
+const MIB: u64 = 1024 * 1024;
+
+// What the machine looks like at one process refresh
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Frame {
+    pub system: SystemInfo,
+    pub processes: Vec<Process>,
+}
+
+// Plays back prepared frames instead of reading the machine, one per process refresh and
+// starting over after the last; for tests and demos
+pub struct ScriptedSource {
+    frames: Vec<Frame>,
+    current: usize,
+    killed: HashSet<Pid>,
+}
+
+impl ScriptedSource {
+    pub fn new(frames: Vec<Frame>) -> Self {
+        let frames = if frames.is_empty() { vec![Frame::default()] } else { frames };
+        // The first refresh moves to the first frame
+        Self { current: frames.len() - 1, frames, killed: HashSet::new() }
+    }
+
+    // A small machine building a project while serving a website
+    pub fn demo() -> Self {
+        Self::new((0..120).map(demo_frame).collect())
+    }
+
+    fn frame(&self) -> &Frame {
+        &self.frames[self.current]
+    }
+
+    fn process(&self, pid: Pid) -> Option<&Process> {
+        self.frame().processes.iter().find(|process| process.pid == pid && !self.killed.contains(&pid))
+    }
+}
+
+impl Source for ScriptedSource {
+    fn system(&mut self) -> SystemInfo {
+        self.frame().system.clone()
+    }
+
+    fn refresh_processes(&mut self, _threads: bool) {
+        self.current = (self.current + 1) % self.frames.len();
+    }
+
+    fn processes(&self, threads: bool) -> Vec<ProcessSample<'_>> {
+        self.frame()
+            .processes
+            .iter()
+            .filter(|process| !self.killed.contains(&process.pid) && (threads || process.kind != ProcessKind::Thread))
+            .map(|process| ProcessSample {
+                pid: process.pid,
+                parent: process.parent,
+                uid: process.uid.clone(),
+                name: &process.name,
+                cpu_usage: process.cpu_usage,
+                memory_usage: process.memory_usage,
+                state: process.state,
+                kind: process.kind,
+                start_time: process.start_time,
+            })
+            .collect()
+    }
+
+    fn user_name(&self, uid: &Uid) -> Option<String> {
+        self.frames
+            .iter()
+            .flat_map(|frame| &frame.processes)
+            .find(|process| process.uid.as_ref() == Some(uid))
+            .and_then(|process| process.user.clone())
+    }
+
+    fn detail(&mut self, pid: Pid) -> Option<ProcessDetail> {
+        let process = self.process(pid)?;
+        let mut parents = Vec::new();
+        let mut current = process.parent;
+        while let Some(parent) = current.and_then(|parent| self.process(parent)) {
+            if parent.pid == pid || parents.iter().any(|(pid, _)| *pid == parent.pid) {
+                break;
+            }
+            parents.push((parent.pid, parent.name.clone()));
+            current = parent.parent;
+        }
+
+        Some(ProcessDetail {
+            pid,
+            name: process.name.clone(),
+            exe: None,
+            cwd: None,
+            root: None,
+            cmdline: vec![process.name.clone()],
+            environ: Vec::new(),
+            user: process.user.clone(),
+            credentials: None,
+            parents,
+            start_time: 0,
+            run_time: 0,
+            state: process.state.to_string(),
+            priority: None,
+            nice: None,
+            cgroups: Vec::new(),
+            namespaces: Vec::new(),
+            limits: Vec::new(),
+        })
+    }
+
+    // Killed processes are left out of every later frame
+    fn kill(&mut self, pid: Pid) -> Result<()> {
+        if self.process(pid).is_none() {
+            bail!("process no longer exists");
+        }
+        self.killed.insert(pid);
+        Ok(())
+    }
+
+    fn stat(&self, pid: Pid) -> Option<Stat> {
+        let process = self.process(pid)?;
+        Some(Stat {
+            name: process.name.clone(),
+            state: process.state,
+            processor: process.last_cpu.unwrap_or(0),
+            ..Stat::default()
+        })
+    }
+
+    fn memory(&self, pid: Pid) -> Option<MemoryBreakdown> {
+        self.process(pid)?.memory
+    }
+
+    fn files(&self, _pid: Pid) -> Result<Vec<FileDescriptor>, String> {
+        Err("Open files are not available from a scripted source".to_string())
+    }
+
+    fn memory_map(&self, _pid: Pid) -> Result<Vec<Mapping>, String> {
+        Err("Memory maps are not available from a scripted source".to_string())
+    }
+
+    fn connections(&self) -> Vec<Connection> {
+        Vec::new()
+    }
+}
+
+// Smooth, repeatable load between 0 and 1 for the given frame and phase
+fn wave(frame: usize, phase: usize) -> f32 {
+    (((frame + phase * 7) as f32 / 6.0).sin() + 1.0) / 2.0
+}
+
+fn demo_frame(frame: usize) -> Frame {
+    let mut processes = Vec::new();
+    let mut add = |pid: u32, parent: u32, user: (u32, &str), name: &str, cpu: f32, rss: u64, kind: ProcessKind| {
+        let memory = (kind == ProcessKind::Process).then(|| MemoryBreakdown {
+            rss,
+            pss: rss * 3 / 4,
+            uss: rss / 2,
+            shared: rss / 2,
+            swap: 0,
+        });
+        processes.push(Process {
+            pid: Pid::from_u32(pid),
+            parent: (parent != 0).then(|| Pid::from_u32(parent)),
+            uid: user.0.to_string().parse().ok(),
+            user: Some(user.1.to_string()),
+            name: name.to_string(),
+            cpu_usage: cpu,
+            memory_usage: if kind == ProcessKind::Process { rss } else { 0 },
+            memory,
+            state: if cpu > 50.0 { 'R' } else if kind == ProcessKind::KernelThread { 'I' } else { 'S' },
+            last_cpu: Some(pid % 4),
+            kind,
+            start_time: 0,
+            depth: 0,
+        });
+    };
+    let root = (0, "root");
+    let demo = (1000, "demo");
+    let www = (33, "www-data");
+    let postgres = (999, "postgres");
+
+    add(1, 0, root, "systemd", 0.1, 12 * MIB, ProcessKind::Process);
+    add(2, 0, root, "kthreadd", 0.0, 0, ProcessKind::KernelThread);
+    add(15, 2, root, "rcu_preempt", 0.2 * wave(frame, 1), 0, ProcessKind::KernelThread);
+    add(87, 2, root, "kworker/1:1-events", 0.5 * wave(frame, 2), 0, ProcessKind::KernelThread);
+    add(612, 1, root, "sshd", 0.0, 9 * MIB, ProcessKind::Process);
+    add(700, 1, root, "nginx", 0.1, 6 * MIB, ProcessKind::Process);
+    for worker in 0..2 {
+        add(701 + worker, 700, www, "nginx", 12.0 * wave(frame, 3 + worker as usize), 14 * MIB, ProcessKind::Process);
+    }
+    add(900, 1, postgres, "postgres", 0.3, 48 * MIB, ProcessKind::Process);
+    for (i, name) in ["postgres: checkpointer", "postgres: walwriter", "postgres: autovacuum"].into_iter().enumerate() {
+        add(901 + i as u32, 900, postgres, name, 2.0 * wave(frame, 5 + i), (20 + 8 * i as u64) * MIB, ProcessKind::Process);
+    }
+    add(1500, 612, demo, "bash", 0.0, 5 * MIB, ProcessKind::Process);
+    add(1501, 1500, demo, "cargo", 1.5, 40 * MIB, ProcessKind::Process);
+    add(1502, 1501, demo, "cargo", 0.3, 40 * MIB, ProcessKind::Thread);
+
+    // Compiler processes come and go, so the list shows some churn
+    for job in 0..3 {
+        let started = frame / 10 * 10 + job * 3;
+        if frame >= started && frame < started + 8 {
+            let pid = 3000 + (frame / 10 * 3 + job) as u32;
+            let cpu = 60.0 + 40.0 * wave(frame, job);
+            add(pid, 1501, demo, "rustc", cpu, (200 + 50 * (frame - started) as u64) * MIB, ProcessKind::Process);
+        }
+    }
+
+    let cores: Vec<Core> = (0..4).map(|core| Core::new(core, 100.0 * wave(frame, core))).collect();
+    let cpu = cores.iter().map(|core| core.usage).sum::<f32>() / cores.len() as f32;
+    let total = 8 * 1024 * MIB;
+    let used = 3 * 1024 * MIB + (1024.0 * wave(frame, 9)) as u64 * MIB;
+    Frame {
+        system: SystemInfo::new(cores, cpu, (used, total)),
+        processes,
+    }
+}
@@ -0,0 +1,85 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : collector/source.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Data sources of the collector for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use sysinfo::{Pid, Uid};
+use crate::{
+    process::{ProcessDetail, ProcessKind},
+    procfs::{self, net::Connection, FileDescriptor, Mapping, MemoryBreakdown, Stat, PROC_ROOT},
+    system::SystemInfo,
+};
+
+// What a source reads of every process on each tick; everything else is asked for
+// separately, only for the processes that need it
+#[derive(Debug, Clone)]
+pub struct ProcessSample<'a> {
+    pub pid: Pid,
+    pub parent: Option<Pid>,
+    pub uid: Option<Uid>,
+    pub name: &'a str,
+    pub cpu_usage: f32,
+    pub memory_usage: u64,
+    pub state: char,
+    pub kind: ProcessKind,
+    // Differs from the last refresh when the pid now belongs to another process
+    pub start_time: u64,
+}
+
+// Where the collector thread reads system and process data from. The per-process readers
+// and views default to the proc filesystem of this machine
+pub trait Source: Send {
+    // Re-reads CPU and memory usage
+    fn system(&mut self) -> SystemInfo;
+
+    // Re-reads the process list; user-land threads are only read when `threads`
+    fn refresh_processes(&mut self, threads: bool);
+
+    // The processes of the last refresh
+    fn processes(&self, threads: bool) -> Vec<ProcessSample<'_>>;
+
+    fn user_name(&self, uid: &Uid) -> Option<String>;
+
+    fn detail(&mut self, pid: Pid) -> Option<ProcessDetail>;
+
+    fn kill(&mut self, pid: Pid) -> Result<()>;
+
+    fn stat(&self, pid: Pid) -> Option<Stat> {
+        procfs::read_stat(&host_dir(pid))
+    }
+
+    fn memory(&self, pid: Pid) -> Option<MemoryBreakdown> {
+        procfs::read_smaps_rollup(&host_dir(pid))
+    }
+
+    fn files(&self, pid: Pid) -> Result<Vec<FileDescriptor>, String> {
+        procfs::read_fds(&host_dir(pid)).map_err(|err| format!("Cannot read /proc/{}/fd: {}", pid, err))
+    }
+
+    fn memory_map(&self, pid: Pid) -> Result<Vec<Mapping>, String> {
+        procfs::read_smaps(&host_dir(pid)).map_err(|err| format!("Cannot read /proc/{}/smaps: {}", pid, err))
+    }
+
+    fn connections(&self) -> Vec<Connection> {
+        procfs::net::read_connections(Path::new(PROC_ROOT))
+    }
+}
+
+// /proc/<pid> of this machine
+fn host_dir(pid: Pid) -> PathBuf {
+    procfs::pid_path(Path::new(PROC_ROOT), pid.as_u32())
+}
@@ -0,0 +1,154 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : collector/sysinfo_source.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : sysinfo data source for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use anyhow::{anyhow, bail, Result};
+use sysinfo::{
+    CpuRefreshKind, MemoryRefreshKind, Pid, ProcessRefreshKind, ProcessStatus, RefreshKind, System, ThreadKind, Uid,
+    UpdateKind, Users,
+};
+use crate::{
+    process::{ProcessDetail, ProcessKind},
+    system::SystemInfo,
+};
+use super::{ProcessSample, Source};
+
+// The default source, portable to every platform sysinfo supports
+pub struct SysinfoSource {
+    // The only System of the program, refreshed for CPU, memory and processes alike
+    system: System,
+    users: Users,
+    physical_core_count: usize,
+}
+
+impl Default for SysinfoSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SysinfoSource {
+    pub fn new() -> Self {
+        let mut system = System::new_with_specifics(
+            RefreshKind::new()
+                .with_cpu(CpuRefreshKind::everything())
+                .with_memory(MemoryRefreshKind::everything())
+                .with_processes(tick_refresh_kind())
+        );
+        system.refresh_cpu();
+        let physical_core_count = system.physical_core_count().unwrap_or(1);
+
+        Self {
+            system,
+            users: Users::new_with_refreshed_list(),
+            physical_core_count,
+        }
+    }
+}
+
+impl Source for SysinfoSource {
+    fn system(&mut self) -> SystemInfo {
+        self.system.refresh_cpu();
+        self.system.refresh_memory();
+        SystemInfo::read(&self.system, self.physical_core_count)
+    }
+
+    // sysinfo always lists the threads along with their process
+    fn refresh_processes(&mut self, _threads: bool) {
+        self.system.refresh_processes_specifics(tick_refresh_kind());
+    }
+
+    fn processes(&self, threads: bool) -> Vec<ProcessSample<'_>> {
+        self.system
+            .processes()
+            .iter()
+            .filter_map(|(pid, process)| {
+                let kind = match process.thread_kind() {
+                    Some(ThreadKind::Userland) if !threads => return None,
+                    Some(ThreadKind::Userland) => ProcessKind::Thread,
+                    Some(ThreadKind::Kernel) => ProcessKind::KernelThread,
+                    None => ProcessKind::Process,
+                };
+                Some(ProcessSample {
+                    pid: *pid,
+                    parent: process.parent(),
+                    uid: process.user_id().cloned(),
+                    name: process.name(),
+                    cpu_usage: process.cpu_usage(),
+                    memory_usage: process.memory(),
+                    state: status_char(process.status()),
+                    kind,
+                    start_time: process.start_time(),
+                })
+            })
+            .collect()
+    }
+
+    fn user_name(&self, uid: &Uid) -> Option<String> {
+        self.users.get_user_by_id(uid).map(|user| user.name().to_string())
+    }
+
+    // Re-reads the slow-changing fields of a single process before building its detail
+    fn detail(&mut self, pid: Pid) -> Option<ProcessDetail> {
+        self.system.refresh_process_specifics(
+            pid,
+            ProcessRefreshKind::new()
+                .with_cmd(UpdateKind::OnlyIfNotSet)
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_user(UpdateKind::OnlyIfNotSet)
+                .with_environ(UpdateKind::Always)
+                .with_cwd(UpdateKind::Always)
+                .with_root(UpdateKind::Always),
+        );
+        let process = self.system.process(pid)?;
+        Some(ProcessDetail::new(pid, process, &self.system, &self.users))
+    }
+
+    fn kill(&mut self, pid: Pid) -> Result<()> {
+        let process = self.system.process(pid).ok_or_else(|| anyhow!("process no longer exists"))?;
+        if !process.kill() {
+            bail!("the signal could not be sent, the process may belong to another user");
+        }
+        Ok(())
+    }
+}
+
+// Fields read for every process on every tick; the command line, environment and the like are
+// only read for the detail view, and the user only once
+fn tick_refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::new()
+        .with_cpu()
+        .with_memory()
+        .with_user(UpdateKind::OnlyIfNotSet)
+}
+
+// Single-letter state codes as shown by ps and htop
+fn status_char(status: ProcessStatus) -> char {
+    match status {
+        ProcessStatus::Run => 'R',
+        ProcessStatus::Sleep => 'S',
+        ProcessStatus::Idle => 'I',
+        ProcessStatus::Stop => 'T',
+        ProcessStatus::Tracing => 't',
+        ProcessStatus::Zombie => 'Z',
+        ProcessStatus::Dead => 'X',
+        ProcessStatus::UninterruptibleDiskSleep => 'D',
+        ProcessStatus::Parked => 'P',
+        ProcessStatus::Wakekill | ProcessStatus::Waking => 'W',
+        ProcessStatus::LockBlocked => 'L',
+        _ => '?',
+    }
+}
@@ -0,0 +1,495 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : config/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Persistent TOML configuration for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use toml::{Table, Value};
+use crate::{
+    cli::{Order, SortKey},
+    collector::{Intervals, DEFAULT_DETAILS_INTERVAL},
+    process::Column,
+    ui::Meter,
+};
+
+pub const DEFAULT_DELAY: u64 = 250;
+pub const MIN_DELAY: u64 = 50;
+
+const APP_DIR: &str = "blackwin-htop";
+const FILE_NAME: &str = "config.toml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub delay: u64,
+    pub sort_key: Option<SortKey>,
+    pub sort_order: Option<Order>,
+    pub columns: Vec<Column>,
+    pub meters: Vec<Meter>,
+    pub meter_columns: usize,
+    pub theme: String,
+    pub filter: String,
+    pub users: Vec<String>,
+    pub tree: bool,
+    pub show_threads: bool,
+    pub hide_kernel_threads: bool,
+    pub secret_patterns: Vec<String>,
+    pub log_file: Option<PathBuf>,
+    pub intervals: Intervals,
+    // Key bindings are kept as written and interpreted by the keymap
+    pub keys: Table,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            delay: DEFAULT_DELAY,
+            sort_key: None,
+            sort_order: None,
+            columns: Column::DEFAULT.to_vec(),
+            meters: Meter::ALL.to_vec(),
+            meter_columns: 3,
+            theme: "cyberpunk".to_string(),
+            filter: String::new(),
+            users: Vec::new(),
+            tree: false,
+            show_threads: false,
+            hide_kernel_threads: false,
+            secret_patterns: Vec::new(),
+            log_file: None,
+            intervals: Intervals::default(),
+            keys: Table::new(),
+        }
+    }
+}
+
+impl Config {
+    // Reads every known key on its own so one bad entry only loses that entry
+    pub fn parse(text: &str) -> Result<(Self, Vec<String>)> {
+        let table: Table = text.parse().map_err(|err: toml::de::Error| {
+            // Condense the multi-line report to fit the status bar
+            let line = err.span().map_or(1, |span| text[..span.start].lines().count().max(1));
+            anyhow::anyhow!("line {}: {}", line, err.message().trim())
+        })?;
+        let mut reader = Reader { table, warnings: Vec::new() };
+        let mut config = Config::default();
+
+        if let Some(delay) = reader.get("delay", "an integer of at least 50", milliseconds) {
+            config.delay = delay;
+        }
+        config.sort_key = reader.get("sort_key", "a column name", |value| {
+            value.as_str().and_then(|name| SortKey::from_str(name, true).ok())
+        });
+        config.sort_order = reader.get("sort_order", "\"asc\" or \"desc\"", |value| {
+            value.as_str().and_then(|name| Order::from_str(name, true).ok())
+        });
+        if let Some(columns) = reader.names("columns", Column::from_name) {
+            if columns.is_empty() {
+                reader.warn("columns: no valid column given, using the defaults".to_string());
+            } else {
+                config.columns = columns;
+            }
+        }
+        if let Some(meters) = reader.names("meters", Meter::from_name) {
+            config.meters = meters;
+        }
+        if let Some(columns) = reader.get("meter_columns", "an integer between 1 and 8", |value| {
+            value.as_integer().filter(|n| (1..=8).contains(n)).map(|n| n as usize)
+        }) {
+            config.meter_columns = columns;
+        }
+        if let Some(theme) = reader.get("theme", "a string", string) {
+            config.theme = theme;
+        }
+        if let Some(filter) = reader.get("filter", "a string", string) {
+            config.filter = filter;
+        }
+        if let Some(users) = reader.get("users", "a list of strings", strings) {
+            config.users = users;
+        }
+        if let Some(tree) = reader.get("tree", "true or false", Value::as_bool) {
+            config.tree = tree;
+        }
+        if let Some(show) = reader.get("show_threads", "true or false", Value::as_bool) {
+            config.show_threads = show;
+        }
+        if let Some(hide) = reader.get("hide_kernel_threads", "true or false", Value::as_bool) {
+            config.hide_kernel_threads = hide;
+        }
+        if let Some(patterns) = reader.get("secret_patterns", "a list of strings", strings) {
+            config.secret_patterns = patterns;
+        }
+        config.log_file = reader.get("log_file", "a path", |value| value.as_str().map(PathBuf::from));
+        if let Some(table) = reader.get("intervals", "a table", |value| value.as_table().cloned()) {
+            let (intervals, warnings) = parse_intervals(table);
+            config.intervals = intervals;
+            reader.warnings.extend(warnings);
+        }
+        if let Some(keys) = reader.get("keys", "a table", |value| value.as_table().cloned()) {
+            config.keys = keys;
+        }
+
+        for key in reader.table.keys() {
+            reader.warnings.push(format!("{}: unknown setting", key));
+        }
+        Ok((config, reader.warnings))
+    }
+
+    fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert("delay".into(), Value::Integer(self.delay as i64));
+        if let Some(key) = self.sort_key.and_then(|key| key.to_possible_value()) {
+            table.insert("sort_key".into(), Value::String(key.get_name().to_string()));
+        }
+        if let Some(order) = self.sort_order.and_then(|order| order.to_possible_value()) {
+            table.insert("sort_order".into(), Value::String(order.get_name().to_string()));
+        }
+        table.insert("columns".into(), names(self.columns.iter().map(|column| column.name())));
+        table.insert("meters".into(), names(self.meters.iter().map(|meter| meter.name())));
+        table.insert("meter_columns".into(), Value::Integer(self.meter_columns as i64));
+        table.insert("theme".into(), Value::String(self.theme.clone()));
+        table.insert("filter".into(), Value::String(self.filter.clone()));
+        table.insert("users".into(), names(self.users.iter().map(String::as_str)));
+        table.insert("tree".into(), Value::Boolean(self.tree));
+        table.insert("show_threads".into(), Value::Boolean(self.show_threads));
+        table.insert("hide_kernel_threads".into(), Value::Boolean(self.hide_kernel_threads));
+        table.insert("secret_patterns".into(), names(self.secret_patterns.iter().map(String::as_str)));
+        if let Some(path) = &self.log_file {
+            table.insert("log_file".into(), Value::String(path.display().to_string()));
+        }
+        table.insert("intervals".into(), Value::Table(intervals_to_toml(self.intervals)));
+        table.insert("keys".into(), Value::Table(self.keys.clone()));
+        table
+    }
+}
+
+// The configuration together with the file it came from
+pub struct ConfigFile {
+    pub path: Option<PathBuf>,
+    pub config: Config,
+    pub warnings: Vec<String>,
+    // A file that failed to parse is never overwritten
+    writable: bool,
+    // The file as read and the settings it gave; saving only replaces the entries whose
+    // setting changed, so malformed and unknown ones are kept as written
+    document: Table,
+    saved: Config,
+}
+
+impl ConfigFile {
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut file = Self {
+            path,
+            config: Config::default(),
+            warnings: Vec::new(),
+            writable: true,
+            document: Table::new(),
+            saved: Config::default(),
+        };
+        let Some(path) = &file.path else {
+            return file;
+        };
+
+        match fs::read_to_string(path) {
+            Ok(text) => match Config::parse(&text) {
+                Ok((config, warnings)) => {
+                    // Parsing succeeded, so the document does as well
+                    file.document = text.parse().unwrap_or_default();
+                    file.saved = config.clone();
+                    file.config = config;
+                    file.warnings = warnings;
+                }
+                Err(err) => {
+                    file.writable = false;
+                    file.warnings.push(format!("{}; using defaults, changes will not be saved", err));
+                }
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                file.writable = false;
+                file.warnings.push(format!("cannot read {}: {}", path.display(), err));
+            }
+        }
+        file
+    }
+
+    // User themes live next to the config file
+    pub fn themes_dir(&self) -> Option<PathBuf> {
+        Some(self.path.as_ref()?.parent()?.join("themes"))
+    }
+
+    pub fn save(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.writable {
+            bail!("{} has errors and was left untouched", path.display());
+        }
+
+        let (current, saved) = (self.config.to_table(), self.saved.to_table());
+        let mut document = self.document.clone();
+        for key in saved.keys().filter(|key| !current.contains_key(*key)) {
+            document.remove(key);
+        }
+        for (key, value) in current {
+            if saved.get(&key) != Some(&value) {
+                document.insert(key, value);
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("cannot create {}", dir.display()))?;
+        }
+        // Write to a temporary file first so a crash never leaves a truncated config
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, toml::to_string(&document)?).with_context(|| format!("cannot write {}", temp.display()))?;
+        fs::rename(&temp, path).with_context(|| format!("cannot write {}", path.display()))?;
+        self.document = document;
+        self.saved = self.config.clone();
+        Ok(())
+    }
+}
+
+// $XDG_CONFIG_HOME/blackwin-htop/config.toml, falling back to ~/.config, or %APPDATA% on Windows
+pub fn default_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    base.map(|dir| dir.join(APP_DIR).join(FILE_NAME))
+}
+
+struct Reader {
+    table: Table,
+    warnings: Vec<String>,
+}
+
+impl Reader {
+    fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    fn get<T>(&mut self, key: &str, expected: &str, parse: impl Fn(&Value) -> Option<T>) -> Option<T> {
+        let value = self.table.remove(key)?;
+        let parsed = parse(&value);
+        if parsed.is_none() {
+            self.warn(format!("{}: expected {}, found {}", key, expected, value));
+        }
+        parsed
+    }
+
+    // A list of names where unknown entries are reported and skipped
+    fn names<T>(&mut self, key: &str, from_name: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+        let names = self.get(key, "a list of strings", strings)?;
+        let mut items = Vec::with_capacity(names.len());
+        for name in names {
+            match from_name(&name) {
+                Some(item) => items.push(item),
+                None => self.warn(format!("{}: unknown entry \"{}\"", key, name)),
+            }
+        }
+        Some(items)
+    }
+}
+
+fn parse_intervals(table: Table) -> (Intervals, Vec<String>) {
+    let mut reader = Reader { table, warnings: Vec::new() };
+    let expected = "an integer of at least 50";
+    let intervals = Intervals {
+        cpu: reader.get("cpu", expected, milliseconds),
+        processes: reader.get("processes", expected, milliseconds),
+        details: reader.get("details", expected, milliseconds).unwrap_or(DEFAULT_DETAILS_INTERVAL),
+        views: reader.get("views", expected, milliseconds),
+    };
+
+    for key in reader.table.keys() {
+        reader.warnings.push(format!("{}: unknown setting", key));
+    }
+    (intervals, reader.warnings.into_iter().map(|warning| format!("intervals.{}", warning)).collect())
+}
+
+fn intervals_to_toml(intervals: Intervals) -> Table {
+    let mut table = Table::new();
+    let entries = [
+        ("cpu", intervals.cpu),
+        ("processes", intervals.processes),
+        ("details", Some(intervals.details)),
+        ("views", intervals.views),
+    ];
+    for (key, interval) in entries {
+        if let Some(interval) = interval {
+            table.insert(key.into(), Value::Integer(interval as i64));
+        }
+    }
+    table
+}
+
+fn milliseconds(value: &Value) -> Option<u64> {
+    value.as_integer().and_then(|ms| u64::try_from(ms).ok()).filter(|ms| *ms >= MIN_DELAY)
+}
+
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array()?.iter().map(string).collect()
+}
+
+fn names<'a>(names: impl Iterator<Item = &'a str>) -> Value {
+    Value::Array(names.map(|name| Value::String(name.to_string())).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_setting() {
+        let text = r#"
+            delay = 500
+            sort_key = "mem"
+            sort_order = "asc"
+            columns = ["pid", "cpu", "name"]
+            meters = ["cpu", "load"]
+            meter_columns = 2
+            theme = "matrix"
+            filter = "ssh"
+            users = ["root", "1000"]
+            tree = true
+            show_threads = true
+            hide_kernel_threads = true
+            secret_patterns = ["*_PIN"]
+            log_file = "/tmp/htop.log"
+
+            [intervals]
+            cpu = 1000
+            details = 5000
+
+            [keys]
+            quit = "C-c"
+        "#;
+        let (config, warnings) = Config::parse(text).unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(config.delay, 500);
+        assert_eq!(config.sort_key, Some(SortKey::Rss));
+        assert_eq!(config.sort_order, Some(Order::Asc));
+        assert_eq!(config.columns, [Column::Pid, Column::Cpu, Column::Name]);
+        assert_eq!(config.meters, [Meter::Cpu, Meter::Load]);
+        assert_eq!(config.meter_columns, 2);
+        assert_eq!(config.theme, "matrix");
+        assert_eq!(config.filter, "ssh");
+        assert_eq!(config.users, ["root", "1000"]);
+        assert!(config.tree && config.show_threads && config.hide_kernel_threads);
+        assert_eq!(config.secret_patterns, ["*_PIN"]);
+        assert_eq!(config.log_file, Some(PathBuf::from("/tmp/htop.log")));
+        assert_eq!(
+            config.intervals,
+            Intervals { cpu: Some(1000), processes: None, details: 5000, views: None }
+        );
+        assert_eq!(config.keys.get("quit").and_then(Value::as_str), Some("C-c"));
+    }
+
+    #[test]
+    fn empty_file_gives_defaults() {
+        assert_eq!(Config::parse("").unwrap(), (Config::default(), Vec::new()));
+    }
+
+    #[test]
+    fn reports_and_skips_bad_entries() {
+        let cases: [(&str, &str); 10] = [
+            ("delay = 10", "delay: expected an integer of at least 50, found 10"),
+            ("delay = \"fast\"", "delay: expected an integer of at least 50, found \"fast\""),
+            ("sort_key = \"size\"", "sort_key: expected a column name, found \"size\""),
+            ("sort_order = \"up\"", "sort_order: expected \"asc\" or \"desc\", found \"up\""),
+            ("columns = []", "columns: no valid column given, using the defaults"),
+            ("meter_columns = 9", "meter_columns: expected an integer between 1 and 8, found 9"),
+            ("tree = \"yes\"", "tree: expected true or false, found \"yes\""),
+            ("users = [1, 2]", "users: expected a list of strings, found [1, 2]"),
+            ("colour = \"red\"", "colour: unknown setting"),
+            ("[intervals]\ncpu = 5\nnet = 100", "intervals.cpu: expected an integer of at least 50, found 5"),
+        ];
+        for (text, warning) in cases {
+            let (config, warnings) = Config::parse(text).unwrap();
+            assert!(warnings.iter().any(|w| w == warning), "{}: {:?}", text, warnings);
+            // The other settings keep their defaults
+            assert_eq!(config.delay, DEFAULT_DELAY, "{}", text);
+            assert_eq!(config.columns, Column::DEFAULT, "{}", text);
+        }
+    }
+
+    #[test]
+    fn keeps_valid_names_of_a_list() {
+        let (config, warnings) = Config::parse("columns = [\"pid\", \"colour\", \"name\"]").unwrap();
+        assert_eq!(config.columns, [Column::Pid, Column::Name]);
+        assert_eq!(warnings, ["columns: unknown entry \"colour\""]);
+
+        let (_, warnings) = Config::parse("[intervals]\nnet = 100").unwrap();
+        assert_eq!(warnings, ["intervals.net: unknown setting"]);
+    }
+
+    #[test]
+    fn syntax_errors_name_the_line() {
+        let err = Config::parse("delay = 500\ntheme = \n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2: "), "{}", err);
+    }
+
+    #[test]
+    fn saving_keeps_unchanged_entries() {
+        let dir = env::temp_dir().join(format!("blackwin-htop-config-{}", std::process::id()));
+        let path = dir.join(FILE_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "delay = 10\ncolour = \"red\"\ntheme = \"matrix\"\n").unwrap();
+
+        let mut file = ConfigFile::load(Some(path.clone()));
+        assert_eq!(file.warnings.len(), 2);
+        file.config.tree = true;
+        file.save().unwrap();
+        file.config.theme = "light".to_string();
+        file.save().unwrap();
+
+        let saved: Table = fs::read_to_string(&path).unwrap().parse().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(saved.get("delay"), Some(&Value::Integer(10)));
+        assert_eq!(saved.get("colour").and_then(Value::as_str), Some("red"));
+        assert_eq!(saved.get("theme").and_then(Value::as_str), Some("light"));
+        assert_eq!(saved.get("tree"), Some(&Value::Boolean(true)));
+        assert!(!saved.contains_key("columns"));
+    }
+
+    #[test]
+    fn unparsable_files_are_not_overwritten() {
+        let dir = env::temp_dir().join(format!("blackwin-htop-broken-{}", std::process::id()));
+        let path = dir.join(FILE_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "delay = \n").unwrap();
+
+        let mut file = ConfigFile::load(Some(path.clone()));
+        file.config.tree = true;
+        let result = file.save();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+        assert_eq!(text, "delay = \n");
+    }
+}
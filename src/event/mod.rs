@@ -15,13 +15,17 @@
  **********************************************************************
  */
 
-use std::{
+#[cfg(unix)]
+use std::thread;
+use std::time::Duration;
+use anyhow::{bail, Result};
+use crossterm::event::{self, EventStream, KeyEvent, KeyEventKind, MouseEvent};
+use futures::StreamExt;
+use tokio::{
     sync::mpsc,
-    thread,
-    time::{Duration, Instant},
+    time::{self, Interval, MissedTickBehavior},
 };
-use anyhow::Result;
-use crossterm::event::{self, KeyEvent};
+use crate::collector::Update;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum InputMode {
@@ -80,56 +84,116 @@ impl InputState {
 
 pub enum Event {
     Input(KeyEvent),
-    Tick,
+    Mouse(MouseEvent),
+    Resize,
+    FocusGained,
+    FocusLost,
+    Paste(String),
+    // New data or an answer from the collector thread
+    Collector(Update),
+    // Time to redraw, only sent while something changed
+    Frame,
+    // SIGTERM, SIGHUP or SIGINT from outside
+    Terminate(i32),
+    // SIGTSTP from outside, as Ctrl-Z itself arrives as a key in raw mode
+    Suspend,
+    // SIGCONT after being stopped, when the screen has to be set up again
+    Resume,
 }
 
+// Redraws are capped at about 30 frames per second, however fast input arrives
+const FRAME_INTERVAL: Duration = Duration::from_millis(33);
+
 pub struct EventHandler {
-    rx: mpsc::Receiver<Event>,
-    _tx: mpsc::Sender<Event>,
-    _input_handle: thread::JoinHandle<()>,
-    _tick_handle: thread::JoinHandle<()>,
+    events: EventStream,
+    updates: mpsc::UnboundedReceiver<Update>,
+    frame: Interval,
+    signals: mpsc::UnboundedReceiver<Event>,
+    #[cfg(unix)]
+    signal_handle: signal_hook::iterator::Handle,
 }
 
 impl EventHandler {
-    pub fn new(tick_rate: Duration) -> Self {
-        let (tx, rx) = mpsc::channel();
-        let input_tx = tx.clone();
-
-        let input_handle = thread::spawn(move || {
-            let mut last_input = Instant::now();
-            loop {
-                if event::poll(Duration::from_millis(50)).expect("failed to poll events") {
-                    if let Ok(key) = event::read() {
-                        if let event::Event::Key(key) = key {
-                            let now = Instant::now();
-                            // Increase debounce time to 150ms
-                            if now.duration_since(last_input) >= Duration::from_millis(150) {
-                                input_tx.send(Event::Input(key)).expect("failed to send input event");
-                                last_input = now;
-                            }
-                        }
-                    }
-                }
-            }
-        });
+    pub fn new(updates: mpsc::UnboundedReceiver<Update>) -> Result<Self> {
+        let mut frame = time::interval(FRAME_INTERVAL);
+        frame.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
-        let tick_handle = {
-            let tx = tx.clone();
-            thread::spawn(move || loop {
-                tx.send(Event::Tick).expect("failed to send tick event");
-                thread::sleep(tick_rate);
-            })
-        };
+        let (tx, signals) = mpsc::unbounded_channel();
+        #[cfg(unix)]
+        let signal_handle = forward_signals(tx)?;
+        #[cfg(not(unix))]
+        drop(tx);
 
-        Self {
-            rx,
-            _tx: tx,
-            _input_handle: input_handle,
-            _tick_handle: tick_handle,
+        Ok(Self {
+            events: EventStream::new(),
+            updates,
+            frame,
+            signals,
+            #[cfg(unix)]
+            signal_handle,
+        })
+    }
+
+    // Waits for the next terminal event, collector update or frame; frames are only due when `redraw` is set
+    pub async fn next(&mut self, redraw: bool) -> Result<Event> {
+        loop {
+            let event = tokio::select! {
+                event = self.events.next() => match event {
+                    Some(event) => convert(event?),
+                    None => bail!("terminal input closed"),
+                },
+                update = self.updates.recv() => match update {
+                    Some(update) => Some(Event::Collector(update)),
+                    None => bail!("the collector thread stopped"),
+                },
+                _ = self.frame.tick(), if redraw => Some(Event::Frame),
+                Some(event) = self.signals.recv() => Some(event),
+            };
+            if let Some(event) = event {
+                return Ok(event);
+            }
         }
     }
+}
+
+#[cfg(unix)]
+impl Drop for EventHandler {
+    fn drop(&mut self) {
+        self.signal_handle.close();
+    }
+}
 
-    pub fn next(&self) -> Result<Event> {
-        Ok(self.rx.recv()?)
+// Signal handlers may only do very little, so a thread turns them into events
+#[cfg(unix)]
+fn forward_signals(tx: mpsc::UnboundedSender<Event>) -> Result<signal_hook::iterator::Handle> {
+    use signal_hook::consts::{SIGCONT, SIGHUP, SIGINT, SIGTERM, SIGTSTP};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGTERM, SIGHUP, SIGINT, SIGTSTP, SIGCONT])?;
+    let handle = signals.handle();
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGTSTP => Event::Suspend,
+                SIGCONT => Event::Resume,
+                signal => Event::Terminate(signal),
+            };
+            if tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(handle)
+}
+
+fn convert(event: event::Event) -> Option<Event> {
+    match event {
+        // Windows reports key releases as well; held keys arrive as presses or repeats
+        event::Event::Key(key) if key.kind == KeyEventKind::Release => None,
+        event::Event::Key(key) => Some(Event::Input(key)),
+        event::Event::Mouse(mouse) => Some(Event::Mouse(mouse)),
+        event::Event::Resize(_, _) => Some(Event::Resize),
+        event::Event::FocusGained => Some(Event::FocusGained),
+        event::Event::FocusLost => Some(Event::FocusLost),
+        event::Event::Paste(text) => Some(Event::Paste(text)),
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,177 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : export/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : JSON and CSV export of the process list for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use serde::Serialize;
+use crate::{
+    cli::OutputFormat,
+    collector::Snapshot,
+    process::{format_timestamp, Column, Process},
+    system::SystemInfo,
+};
+
+// A snapshot as written to JSON: the system readings and the listed rows, in their order
+#[derive(Serialize)]
+struct Document<'a> {
+    time: u64,
+    system: &'a SystemInfo,
+    processes: &'a [Process],
+}
+
+// Writes the rows of one snapshot; CSV has the shown columns and only starts with the header
+// line when `header` is set
+pub fn write(
+    out: &mut impl Write,
+    format: OutputFormat,
+    snapshot: &Snapshot,
+    rows: &[Process],
+    columns: &[Column],
+    header: bool,
+) -> io::Result<()> {
+    let document = Document { time: snapshot.time, system: &snapshot.system, processes: rows };
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, &document)?;
+            writeln!(out)
+        }
+        OutputFormat::Ndjson => {
+            serde_json::to_writer(&mut *out, &document)?;
+            writeln!(out)
+        }
+        OutputFormat::Csv => {
+            if header {
+                let names: Vec<&str> = columns.iter().map(|column| column.name()).collect();
+                writeln!(out, "time,{}", names.join(","))?;
+            }
+            for process in rows {
+                let cells: Vec<String> = columns.iter().map(|column| csv_field(column.raw_value(process))).collect();
+                writeln!(out, "{},{}", snapshot.time, cells.join(","))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// Writes the rows as JSON to a new file in the current directory named after the snapshot time;
+// a number is added to the name rather than replacing a file written in the same second
+pub fn to_file(snapshot: &Snapshot, rows: &[Process]) -> io::Result<PathBuf> {
+    to_dir(&env::current_dir()?, snapshot, rows)
+}
+
+fn to_dir(dir: &Path, snapshot: &Snapshot, rows: &[Process]) -> io::Result<PathBuf> {
+    let stamp = format_timestamp(snapshot.time).trim_end_matches(" UTC").replace(' ', "_").replace(':', "-");
+    let mut attempt = 0;
+    let (file, path) = loop {
+        let name = match attempt {
+            0 => format!("blackwin-htop-{}.json", stamp),
+            _ => format!("blackwin-htop-{}-{}.json", stamp, attempt),
+        };
+        let path = dir.join(name);
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => break (file, path),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(err) => return Err(err),
+        }
+    };
+    let mut file = BufWriter::new(file);
+    write(&mut file, OutputFormat::Json, snapshot, rows, &[], false)?;
+    file.flush()?;
+    Ok(path)
+}
+
+fn csv_field(value: String) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sysinfo::Pid;
+    use crate::process::ProcessKind;
+
+    fn process(pid: u32, name: &str) -> Process {
+        Process {
+            pid: Pid::from_u32(pid),
+            parent: None,
+            uid: None,
+            user: Some("root".to_string()),
+            name: name.to_string(),
+            cpu_usage: 1.5,
+            memory_usage: 0,
+            memory: None,
+            state: 'S',
+            last_cpu: None,
+            kind: ProcessKind::Process,
+            start_time: 0,
+            depth: 0,
+        }
+    }
+
+    fn snapshot(time: u64) -> Snapshot {
+        Snapshot { time, ..Snapshot::default() }
+    }
+
+    fn csv(snapshot: &Snapshot, rows: &[Process], header: bool) -> String {
+        let mut out = Vec::new();
+        write(&mut out, OutputFormat::Csv, snapshot, rows, &[Column::Pid, Column::Cpu, Column::Name], header).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn quotes_csv_fields() {
+        assert_eq!(csv_field("nginx".to_string()), "nginx");
+        assert_eq!(csv_field("a,b".to_string()), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\"".to_string()), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines".to_string()), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r".to_string()), "\"cr\r\"");
+    }
+
+    #[test]
+    fn writes_the_csv_header_once() {
+        let rows = [process(1, "init"), process(42, "web, api")];
+        assert_eq!(csv(&snapshot(100), &rows, true), "time,pid,cpu,name\n100,1,1.5,init\n100,42,1.5,\"web, api\"\n");
+        assert_eq!(csv(&snapshot(102), &rows[..1], false), "102,1,1.5,init\n");
+    }
+
+    #[test]
+    fn numbers_files_of_the_same_second() {
+        let dir = env::temp_dir().join(format!("blackwin-htop-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let snapshot = snapshot(0);
+        let rows = [process(1, "init")];
+
+        let first = to_dir(&dir, &snapshot, &rows).unwrap();
+        let second = to_dir(&dir, &snapshot, &rows).unwrap();
+        let third = to_dir(&dir, &snapshot, &rows).unwrap();
+        let name = |path: &PathBuf| path.file_name().unwrap().to_string_lossy().into_owned();
+        assert_eq!(name(&first), "blackwin-htop-1970-01-01_00-00-00.json");
+        assert_eq!(name(&second), "blackwin-htop-1970-01-01_00-00-00-1.json");
+        assert_eq!(name(&third), "blackwin-htop-1970-01-01_00-00-00-2.json");
+
+        let document: serde_json::Value = serde_json::from_str(&fs::read_to_string(&second).unwrap()).unwrap();
+        assert_eq!(document["processes"][0]["name"], "init");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
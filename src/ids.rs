@@ -0,0 +1,54 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ids.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Reading back pids and uids for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+// sysinfo writes pids and uids as strings but cannot read them back; these read them
+// for #[serde(deserialize_with)]
+
+use std::{collections::HashMap, hash::Hash, str::FromStr, sync::Arc};
+use serde::{de::Error, Deserialize, Deserializer};
+
+fn parse<T: FromStr, E: Error>(text: &str) -> Result<T, E> {
+    text.parse().map_err(|_| E::custom(format!("invalid id {:?}", text)))
+}
+
+pub fn id<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<T, D::Error> {
+    parse(&String::deserialize(deserializer)?)
+}
+
+pub fn optional_id<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<Option<T>, D::Error> {
+    Option::<String>::deserialize(deserializer)?.map(|text| parse(&text)).transpose()
+}
+
+pub fn named_ids<'de, D: Deserializer<'de>, T: FromStr>(deserializer: D) -> Result<Vec<(T, String)>, D::Error> {
+    Vec::<(String, String)>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(id, name)| Ok((parse(&id)?, name)))
+        .collect()
+}
+
+pub fn id_map<'de, D, K, V>(deserializer: D) -> Result<Arc<HashMap<K, V>>, D::Error>
+where
+    D: Deserializer<'de>,
+    K: FromStr + Eq + Hash,
+    V: Deserialize<'de>,
+{
+    HashMap::<String, V>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(id, value)| Ok((parse(&id)?, value)))
+        .collect::<Result<_, _>>()
+        .map(Arc::new)
+}
@@ -0,0 +1,520 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : keymap/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Remappable key bindings for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::fmt;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use toml::{Table, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Search,
+    Down,
+    Up,
+    PageDown,
+    PageUp,
+    Home,
+    End,
+    SortPid,
+    SortName,
+    SortCpu,
+    SortMemory,
+    InvertSort,
+    ToggleTree,
+    NextTheme,
+    ToggleThreads,
+    ToggleKernelThreads,
+    Details,
+    Environment,
+    Files,
+    MemoryMap,
+    Network,
+    Log,
+    CloseView,
+    NextTab,
+    ViewSearch,
+    RevealSecrets,
+    SortMappings,
+    GroupMappings,
+    Kill,
+    IncreaseDelay,
+    DecreaseDelay,
+    Pause,
+    Export,
+    Suspend,
+    Debug,
+    Help,
+}
+
+// Default bindings, in the order the actions are listed
+const DEFAULT_BINDINGS: [(Action, &[&str]); 37] = [
+    (Action::Quit, &["q", "Q"]),
+    (Action::Search, &["F3"]),
+    (Action::Down, &["j", "Down"]),
+    (Action::Up, &["k", "Up"]),
+    (Action::PageDown, &["PageDown"]),
+    (Action::PageUp, &["PageUp"]),
+    (Action::Home, &["Home", "gg"]),
+    (Action::End, &["End", "G"]),
+    (Action::SortPid, &["p", "P"]),
+    (Action::SortName, &["n", "N"]),
+    (Action::SortCpu, &["c", "C"]),
+    (Action::SortMemory, &["m", "M"]),
+    (Action::InvertSort, &["I"]),
+    (Action::ToggleTree, &["t"]),
+    (Action::NextTheme, &["T"]),
+    (Action::ToggleThreads, &["H"]),
+    (Action::ToggleKernelThreads, &["K"]),
+    (Action::Details, &["Enter"]),
+    (Action::Environment, &["e"]),
+    (Action::Files, &["l"]),
+    (Action::MemoryMap, &["v"]),
+    (Action::Network, &["o"]),
+    (Action::Log, &["L"]),
+    (Action::CloseView, &["Esc"]),
+    (Action::NextTab, &["Tab"]),
+    (Action::ViewSearch, &["/"]),
+    (Action::RevealSecrets, &["r"]),
+    (Action::SortMappings, &["s"]),
+    (Action::GroupMappings, &["f"]),
+    (Action::Kill, &["F9"]),
+    (Action::IncreaseDelay, &["+"]),
+    (Action::DecreaseDelay, &["-"]),
+    (Action::Pause, &["Z"]),
+    (Action::Export, &["E"]),
+    (Action::Suspend, &["C-z"]),
+    (Action::Debug, &["D"]),
+    (Action::Help, &["F1"]),
+];
+
+impl Action {
+    // Identifier used in the [keys] table of the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Search => "search",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::Home => "home",
+            Action::End => "end",
+            Action::SortPid => "sort_pid",
+            Action::SortName => "sort_name",
+            Action::SortCpu => "sort_cpu",
+            Action::SortMemory => "sort_memory",
+            Action::InvertSort => "invert_sort",
+            Action::ToggleTree => "toggle_tree",
+            Action::NextTheme => "next_theme",
+            Action::ToggleThreads => "toggle_threads",
+            Action::ToggleKernelThreads => "toggle_kernel_threads",
+            Action::Details => "details",
+            Action::Environment => "environment",
+            Action::Files => "files",
+            Action::MemoryMap => "memory_map",
+            Action::Network => "network",
+            Action::Log => "log",
+            Action::CloseView => "close_view",
+            Action::NextTab => "next_tab",
+            Action::ViewSearch => "view_search",
+            Action::RevealSecrets => "reveal_secrets",
+            Action::SortMappings => "sort_mappings",
+            Action::GroupMappings => "group_mappings",
+            Action::Kill => "kill",
+            Action::IncreaseDelay => "increase_delay",
+            Action::DecreaseDelay => "decrease_delay",
+            Action::Pause => "pause",
+            Action::Export => "export",
+            Action::Suspend => "suspend",
+            Action::Debug => "debug",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DEFAULT_BINDINGS.iter().map(|(action, _)| *action).find(|action| action.name() == name)
+    }
+}
+
+// A single key press; shifted letters are told apart by case alone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        if matches!(event.code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code: event.code, modifiers }
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 19] = [
+    ("Enter", KeyCode::Enter),
+    ("Esc", KeyCode::Esc),
+    ("Escape", KeyCode::Esc),
+    ("Tab", KeyCode::Tab),
+    ("BackTab", KeyCode::BackTab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Del", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PgUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("PgDn", KeyCode::PageDown),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+];
+
+impl Key {
+    // "q", "F3", "PageDown", "Space", "Ctrl-d" or "C-d"
+    fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        while let Some((prefix, key)) = rest.split_once('-').filter(|(_, key)| !key.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "c" | "ctrl" => KeyModifiers::CONTROL,
+                "a" | "m" | "alt" => KeyModifiers::ALT,
+                "s" | "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            rest = key;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ if rest.eq_ignore_ascii_case("space") => KeyCode::Char(' '),
+            _ => match NAMED_KEYS.iter().find(|(name, _)| name.eq_ignore_ascii_case(rest)) {
+                Some((_, code)) => *code,
+                None => KeyCode::F(rest.strip_prefix(['F', 'f'])?.parse().ok().filter(|n| (1..=24).contains(n))?),
+            },
+        };
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                modifiers.remove(KeyModifiers::SHIFT);
+                return Some(Self { code: KeyCode::Char(c.to_ascii_uppercase()), modifiers });
+            }
+        }
+        Some(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "?"),
+            },
+        }
+    }
+}
+
+// A whole binding is a single named key ("PageDown") or a sequence of keys
+// where named keys are written in angle brackets ("gg", "<C-w>j")
+fn parse_sequence(text: &str) -> Option<Vec<Key>> {
+    // "F30" is a function key that does not exist, not the keys F, 3 and 0
+    if text.len() > 1 && text.strip_prefix(['F', 'f']).is_some_and(|n| n.chars().all(|c| c.is_ascii_digit())) {
+        return Key::parse(text).map(|key| vec![key]);
+    }
+    if text.chars().count() > 1 && !text.contains('<') {
+        if let Some(key) = Key::parse(text) {
+            return Some(vec![key]);
+        }
+    }
+
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        match rest.strip_prefix('<').and_then(|inner| inner.split_once('>')) {
+            Some((name, after)) if !name.is_empty() => {
+                keys.push(Key::parse(name)?);
+                rest = after;
+            }
+            _ => {
+                keys.push(Key { code: KeyCode::Char(c), modifiers: KeyModifiers::NONE });
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    (!keys.is_empty()).then_some(keys)
+}
+
+fn describe(keys: &[Key]) -> String {
+    keys.iter().map(Key::to_string).collect()
+}
+
+pub struct Keymap {
+    bindings: Vec<(Vec<Key>, Action)>,
+    // Keys typed so far of a sequence that is not complete yet
+    pending: Vec<Key>,
+}
+
+impl Keymap {
+    // Bindings from the [keys] table replace the defaults of that action and take
+    // their keys away from other actions; problems are returned as warnings
+    pub fn new(keys: &Table) -> (Self, Vec<String>) {
+        let mut warnings = Vec::new();
+        let mut custom: Vec<(Action, Vec<Vec<Key>>)> = Vec::new();
+        for (name, value) in keys {
+            let Some(action) = Action::from_name(name) else {
+                warnings.push(format!("keys.{}: unknown action", name));
+                continue;
+            };
+            let texts: Vec<&str> = match value {
+                Value::String(text) => vec![text.as_str()],
+                Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if texts.is_empty() && !matches!(value, Value::Array(items) if items.is_empty()) {
+                warnings.push(format!("keys.{}: expected a key or a list of keys, found {}", name, value));
+                continue;
+            }
+            let mut sequences = Vec::new();
+            for text in texts {
+                match parse_sequence(text) {
+                    Some(sequence) => sequences.push(sequence),
+                    None => warnings.push(format!("keys.{}: invalid key \"{}\"", name, text)),
+                }
+            }
+            custom.push((action, sequences));
+        }
+
+        let taken: Vec<&Vec<Key>> = custom.iter().flat_map(|(_, sequences)| sequences).collect();
+        let mut bindings = Vec::new();
+        for (action, defaults) in DEFAULT_BINDINGS {
+            match custom.iter().find(|(custom_action, _)| *custom_action == action) {
+                Some((_, sequences)) => bindings.extend(sequences.iter().map(|sequence| (sequence.clone(), action))),
+                None => bindings.extend(
+                    defaults
+                        .iter()
+                        .filter_map(|text| parse_sequence(text))
+                        .filter(|sequence| !taken.contains(&sequence))
+                        .map(|sequence| (sequence, action)),
+                ),
+            }
+        }
+
+        let keymap = Self { bindings, pending: Vec::new() };
+        warnings.extend(keymap.conflicts());
+        (keymap, warnings)
+    }
+
+    // Keys bound to two actions, and sequences that can never complete
+    // because a shorter binding fires first
+    fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (i, (first, first_action)) in self.bindings.iter().enumerate() {
+            for (second, second_action) in &self.bindings[i + 1..] {
+                if first == second && first_action != second_action {
+                    conflicts.push(format!(
+                        "keys: {} is bound to both {} and {}",
+                        describe(first),
+                        first_action.name(),
+                        second_action.name()
+                    ));
+                } else if first.len() != second.len() {
+                    let (short, short_action, long, long_action) = if first.len() < second.len() {
+                        (first, first_action, second, second_action)
+                    } else {
+                        (second, second_action, first, first_action)
+                    };
+                    if long.starts_with(short) {
+                        conflicts.push(format!(
+                            "keys: {} ({}) makes {} ({}) unreachable",
+                            describe(short),
+                            short_action.name(),
+                            describe(long),
+                            long_action.name()
+                        ));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    // Feeds one key press; returns the action once a complete binding was typed
+    pub fn handle(&mut self, event: KeyEvent) -> Option<Action> {
+        let key = Key::from(event);
+        self.pending.push(key);
+        loop {
+            let mut partial = false;
+            for (sequence, action) in &self.bindings {
+                if sequence.starts_with(&self.pending) {
+                    if sequence.len() == self.pending.len() {
+                        self.pending.clear();
+                        return Some(*action);
+                    }
+                    partial = true;
+                }
+            }
+            if partial {
+                return None;
+            }
+            // A broken sequence is dropped, but its last key may start a new one
+            if self.pending.len() == 1 {
+                self.pending.clear();
+                return None;
+            }
+            self.pending = vec![key];
+        }
+    }
+
+    // Forgets a partly typed sequence
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+
+    // First key bound to the action, for the help line
+    pub fn label(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(sequence, _)| describe(sequence))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    fn char(c: char) -> Key {
+        key(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn press(keymap: &mut Keymap, code: KeyCode, modifiers: KeyModifiers) -> Option<Action> {
+        keymap.handle(KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn parses_sequences() {
+        let cases: [(&str, Option<Vec<Key>>); 13] = [
+            ("q", Some(vec![char('q')])),
+            ("C-z", Some(vec![key(KeyCode::Char('z'), KeyModifiers::CONTROL)])),
+            ("Ctrl-d", Some(vec![key(KeyCode::Char('d'), KeyModifiers::CONTROL)])),
+            ("S-a", Some(vec![char('A')])),
+            ("gg", Some(vec![char('g'), char('g')])),
+            ("F3", Some(vec![key(KeyCode::F(3), KeyModifiers::NONE)])),
+            ("PageDown", Some(vec![key(KeyCode::PageDown, KeyModifiers::NONE)])),
+            ("Space", Some(vec![char(' ')])),
+            ("<C-w>j", Some(vec![key(KeyCode::Char('w'), KeyModifiers::CONTROL), char('j')])),
+            ("-", Some(vec![char('-')])),
+            ("F25", None),
+            ("<F25>", None),
+            ("<X-q>", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse_sequence(text), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn formats_keys() {
+        for text in ["C-z", "F3", "PgDn", "Space", "q"] {
+            assert_eq!(describe(&parse_sequence(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn default_bindings() {
+        let (mut keymap, warnings) = Keymap::new(&Table::new());
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(press(&mut keymap, KeyCode::Char('z'), KeyModifiers::CONTROL), Some(Action::Suspend));
+        assert_eq!(press(&mut keymap, KeyCode::F(3), KeyModifiers::NONE), Some(Action::Search));
+        assert_eq!(press(&mut keymap, KeyCode::Char('g'), KeyModifiers::NONE), None);
+        assert_eq!(press(&mut keymap, KeyCode::Char('g'), KeyModifiers::NONE), Some(Action::Home));
+        // Shift is part of the character
+        assert_eq!(press(&mut keymap, KeyCode::Char('G'), KeyModifiers::SHIFT), Some(Action::End));
+    }
+
+    #[test]
+    fn broken_sequence_starts_over() {
+        let (mut keymap, _) = Keymap::new(&Table::new());
+        assert_eq!(press(&mut keymap, KeyCode::Char('g'), KeyModifiers::NONE), None);
+        assert_eq!(press(&mut keymap, KeyCode::Char('j'), KeyModifiers::NONE), Some(Action::Down));
+    }
+
+    #[test]
+    fn custom_bindings() {
+        let keys: Table = toml::from_str(
+            r#"
+            quit = "C-q"
+            help = "q"
+            search = ["/", "F99"]
+            launch = "x"
+            "#,
+        )
+        .unwrap();
+        let (mut keymap, warnings) = Keymap::new(&keys);
+        assert_eq!(warnings, ["keys.search: invalid key \"F99\"", "keys.launch: unknown action"]);
+        assert_eq!(press(&mut keymap, KeyCode::Char('q'), KeyModifiers::NONE), Some(Action::Help));
+        assert_eq!(press(&mut keymap, KeyCode::Char('q'), KeyModifiers::CONTROL), Some(Action::Quit));
+        assert_eq!(press(&mut keymap, KeyCode::Char('/'), KeyModifiers::NONE), Some(Action::Search));
+        assert_eq!(press(&mut keymap, KeyCode::F(3), KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn view_keys_are_bindings() {
+        let (mut keymap, _) = Keymap::new(&Table::new());
+        assert_eq!(press(&mut keymap, KeyCode::Esc, KeyModifiers::NONE), Some(Action::CloseView));
+        assert_eq!(press(&mut keymap, KeyCode::Tab, KeyModifiers::NONE), Some(Action::NextTab));
+        assert_eq!(press(&mut keymap, KeyCode::Char('/'), KeyModifiers::NONE), Some(Action::ViewSearch));
+        assert_eq!(press(&mut keymap, KeyCode::Char('f'), KeyModifiers::NONE), Some(Action::GroupMappings));
+
+        let keys: Table = toml::from_str("close_view = \"x\"\ngroup_mappings = \"g\"").unwrap();
+        let (mut keymap, warnings) = Keymap::new(&keys);
+        assert_eq!(warnings, ["keys: g (group_mappings) makes gg (home) unreachable"]);
+        assert_eq!(press(&mut keymap, KeyCode::Char('x'), KeyModifiers::NONE), Some(Action::CloseView));
+        assert_eq!(press(&mut keymap, KeyCode::Esc, KeyModifiers::NONE), None);
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let keys: Table = toml::from_str("quit = \"g\"").unwrap();
+        let (_, warnings) = Keymap::new(&keys);
+        assert_eq!(warnings, ["keys: g (quit) makes gg (home) unreachable"]);
+    }
+}
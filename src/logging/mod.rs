@@ -0,0 +1,185 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : logging/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Log file and in-app log buffer for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    collections::VecDeque,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use anyhow::{anyhow, Result};
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt},
+    util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+pub const LEVEL_ENV: &str = "BLACKWIN_HTOP_LOG";
+const DEFAULT_LEVEL: &str = "info";
+
+const APP_DIR: &str = "blackwin-htop";
+const FILE_NAME: &str = "blackwin-htop.log";
+
+// Rotated files kept next to the current one
+const MAX_FILES: usize = 7;
+// Lines kept for the log view
+const CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub time: String,
+    pub level: Level,
+    pub message: String,
+}
+
+// Recent log lines, shared between the tracing layer and the log view
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer(Arc<Mutex<VecDeque<LogLine>>>);
+
+impl LogBuffer {
+    fn push(&self, line: LogLine) {
+        let mut lines = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        if lines.len() == CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    // Newest first
+    pub fn lines(&self) -> Vec<LogLine> {
+        let lines = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        lines.iter().rev().cloned().collect()
+    }
+}
+
+pub struct Logging {
+    pub buffer: LogBuffer,
+    pub path: Option<PathBuf>,
+    // Flushes the file when dropped, so it lives until the end of main
+    _guard: Option<WorkerGuard>,
+}
+
+// The level is taken from `level`, then $BLACKWIN_HTOP_LOG, in RUST_LOG syntax ("debug",
+// "blackwin_htop=trace"); a log file that cannot be opened only leaves the log view
+pub fn init(level: Option<&str>, path: Option<PathBuf>) -> Result<(Logging, Option<String>)> {
+    let level = level
+        .map(str::to_string)
+        .or_else(|| env::var(LEVEL_ENV).ok().filter(|level| !level.is_empty()))
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+    let filter = EnvFilter::try_new(&level).map_err(|err| anyhow!("invalid log level '{}': {}", level, err))?;
+
+    let (file, warning) = match path.as_deref().map(open).transpose() {
+        Ok(file) => (file, None),
+        Err(err) => (None, Some(format!("{:#}", err))),
+    };
+    let (writer, guard) = file.map(tracing_appender::non_blocking).unzip();
+    let path = path.filter(|_| writer.is_some());
+
+    let buffer = LogBuffer::default();
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(writer.map(|writer| tracing_subscriber::fmt::layer().with_writer(writer).with_ansi(false)))
+        .with(BufferLayer(buffer.clone()))
+        .try_init()?;
+
+    Ok((Logging { buffer, path, _guard: guard }, warning))
+}
+
+// Rotated daily as blackwin-htop.<date>.log when the path is blackwin-htop.log
+fn open(path: &Path) -> Result<RollingFileAppender> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).map_err(|err| anyhow!("cannot create {}: {}", dir.display(), err))?;
+    let name = |part: Option<&std::ffi::OsStr>| part.and_then(|part| part.to_str()).unwrap_or_default().to_string();
+
+    let mut builder = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(name(path.file_stem()))
+        .max_log_files(MAX_FILES);
+    if path.extension().is_some() {
+        builder = builder.filename_suffix(name(path.extension()));
+    }
+    builder.build(dir).map_err(|err| anyhow!("cannot open log file {}: {}", path.display(), err))
+}
+
+// $XDG_STATE_HOME/blackwin-htop/blackwin-htop.log, falling back to ~/.local/state, or %LOCALAPPDATA% on Windows
+pub fn default_path() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state")))
+    };
+    base.map(|dir| dir.join(APP_DIR).join(FILE_NAME))
+}
+
+struct BufferLayer(LogBuffer);
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.0.push(LogLine {
+            time: clock(),
+            level: *event.metadata().level(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+// The message followed by the other fields as key=value
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value);
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, &format!("{:?}", value));
+    }
+}
+
+impl MessageVisitor {
+    fn record(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push_str(&format!(" {}={}", field.name(), value));
+        }
+    }
+}
+
+// UTC, like the timestamps of the log file
+fn clock() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs());
+    format!("{:02}:{:02}:{:02}", seconds / 3600 % 24, seconds / 60 % 60, seconds % 60)
+}
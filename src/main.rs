@@ -15,9 +15,20 @@
  **********************************************************************
  */
 
-use std::{io, time::Duration};
+use std::{
+    collections::HashSet,
+    io, panic,
+    path::PathBuf,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use crossterm::{
-    event::{KeyEvent, KeyCode},
+    cursor::Show,
+    event::{
+        DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste, EnableFocusChange,
+        EnableMouseCapture, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -25,144 +36,950 @@ use ratatui::{
     backend::CrosstermBackend,
     Terminal,
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
+use clap::Parser;
 
+mod batch;
+mod cli;
+mod config;
 mod ui;
-mod system;
-mod process;
 mod event;
+mod export;
+mod keymap;
+mod logging;
+
+use sysinfo::Pid;
+use blackwin_htop::{collector, process, procfs, system};
 
 use crate::{
-    event::{EventHandler, InputState},
-    process::{ProcessList, SortField},
-    system::SystemInfo,
+    cli::{Options, SourceKind},
+    collector::{Collector, ProcfsSource, ScriptedSource, Snapshot, Source, SysinfoSource, Update, ViewData, Watched},
+    config::{Config, ConfigFile},
+    event::{EventHandler, InputMode, InputState},
+    keymap::{Action, Keymap},
+    logging::LogBuffer,
+    process::{Column, MemoryKind, ProcessDetail, ProcessList, SecretPatterns, SortField, SortOrder},
+    procfs::{net::Connection, FileDescriptor, MapSort, Mapping},
+    ui::{ColorDepth, Meter, Regions, RowCache, Theme},
 };
 
+// Lines moved per mouse wheel step
+const WHEEL_LINES: usize = 3;
+// Delays in milliseconds that + and - step through
+const DELAY_STEPS: [u64; 8] = [50, 100, 250, 500, 1000, 2000, 5000, 10000];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DetailTab {
+    Overview,
+    Environment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    Processes,
+    Detail(Pid),
+    Files(Pid),
+    MemoryMap(Pid),
+    Network,
+    Log,
+}
+
+impl View {
+    // What the collector reads for this view besides the processes
+    fn watched(self) -> Watched {
+        match self {
+            View::Processes | View::Log => Watched::None,
+            View::Detail(pid) => Watched::Detail(pid),
+            View::Files(pid) => Watched::Files(pid),
+            View::MemoryMap(pid) => Watched::MemoryMap(pid),
+            View::Network => Watched::Network,
+        }
+    }
+}
+
+// Settings that can be changed from the UI and are saved to the config file
+#[derive(Debug, Clone, PartialEq)]
+struct Settings {
+    delay: u64,
+    sort: (SortField, SortOrder),
+    tree: bool,
+    show_threads: bool,
+    hide_kernel_threads: bool,
+    filter: String,
+    theme: String,
+}
+
 pub struct App {
     should_quit: bool,
-    system_info: SystemInfo,
+    // Set by Ctrl-Z or SIGTSTP; the event loop owns the terminal and does the suspending
+    should_suspend: bool,
+    collector: Collector,
+    // The latest data from the collector thread, or the one shown when paused
+    snapshot: Arc<Snapshot>,
+    paused: bool,
     process_list: ProcessList,
     input_state: InputState,
+    theme: Theme,
+    theme_name: String,
+    color_depth: ColorDepth,
+    keymap: Keymap,
+    regions: Regions,
+    // First process list row on screen, kept between frames
+    list_offset: usize,
+    // Set whenever the screen needs to be drawn again
+    dirty: bool,
+    read_only: bool,
+    message: Option<String>,
+    config: ConfigFile,
+    settings: Settings,
+    columns: Vec<Column>,
+    meters: Vec<Meter>,
+    meter_columns: usize,
+    view: View,
+    detail: Option<ProcessDetail>,
+    detail_tab: DetailTab,
+    secret_patterns: SecretPatterns,
+    view_query: String,
+    view_searching: bool,
+    reveal_secrets: bool,
+    files: Result<Vec<FileDescriptor>, String>,
+    maps: Result<Vec<Mapping>, String>,
+    map_sort: MapSort,
+    map_grouped: bool,
+    connections: Vec<Connection>,
+    logs: LogBuffer,
+    log_path: Option<PathBuf>,
+    show_debug: bool,
+    // Time spent taking in the last snapshot and drawing the last frame, for the debug overlay
+    apply_time: Duration,
+    draw_time: Duration,
+    // What the collector was last told about the process list, see sync_collector
+    visible_pids: HashSet<Pid>,
+    memory_of_all: bool,
+    view_scroll: u16,
+    view_selected: usize,
 }
 
 impl App {
-    fn new() -> Result<Self> {
+    fn new(
+        options: &Options,
+        mut config: ConfigFile,
+        collector: Collector,
+        logs: LogBuffer,
+        log_path: Option<PathBuf>,
+    ) -> Result<Self> {
+        let mut warnings = std::mem::take(&mut config.warnings);
+
+        // Command-line options take precedence over the config file
+        let themes_dir = config.themes_dir();
+        let (theme_name, theme) = match &options.theme {
+            Some(name) => (name.clone(), Theme::load(name, themes_dir.as_deref())?),
+            None => match Theme::load(&config.config.theme, themes_dir.as_deref()) {
+                Ok(theme) => (config.config.theme.clone(), theme),
+                Err(err) => {
+                    warnings.push(format!("theme: {:#}", err));
+                    (Theme::BUILTIN[0].to_string(), Theme::default())
+                }
+            },
+        };
+        let color_depth = ColorDepth::detect();
+        let (keymap, key_warnings) = Keymap::new(&config.config.keys);
+        warnings.extend(key_warnings);
+
+        let process_list = configured_process_list(options, &config.config);
+        let input_state = InputState {
+            search_input: options.filter.as_ref().unwrap_or(&config.config.filter).clone(),
+            ..InputState::default()
+        };
+
+        let settings = Settings {
+            delay: collector.delay().as_millis() as u64,
+            sort: (process_list.sort_field(), process_list.sort_order()),
+            tree: process_list.tree(),
+            show_threads: process_list.show_threads(),
+            hide_kernel_threads: process_list.hide_kernel_threads(),
+            filter: input_state.search_input.clone(),
+            theme: theme_name.clone(),
+        };
+
+        for warning in &warnings {
+            tracing::warn!("config: {}", warning);
+        }
+        let message = if warnings.is_empty() {
+            None
+        } else {
+            let path = config.path.as_deref().map_or_else(String::new, |path| path.display().to_string());
+            Some(format!("Config {}: {}", path, warnings.join("; ")))
+        };
+
         Ok(Self {
             should_quit: false,
-            system_info: SystemInfo::new(),
-            process_list: ProcessList::new(),
-            input_state: InputState::default(),
+            should_suspend: false,
+            collector,
+            snapshot: Arc::default(),
+            paused: false,
+            process_list,
+            input_state,
+            theme: theme.downgrade(color_depth),
+            theme_name,
+            color_depth,
+            keymap,
+            regions: Regions::default(),
+            list_offset: 0,
+            dirty: true,
+            read_only: options.read_only,
+            message,
+            settings,
+            columns: config.config.columns.clone(),
+            meters: config.config.meters.clone(),
+            meter_columns: config.config.meter_columns,
+            secret_patterns: SecretPatterns::from_env(&config.config.secret_patterns),
+            config,
+            view: View::Processes,
+            detail: None,
+            detail_tab: DetailTab::Overview,
+            view_query: String::new(),
+            view_searching: false,
+            reveal_secrets: false,
+            files: Ok(Vec::new()),
+            maps: Ok(Vec::new()),
+            map_sort: MapSort::Rss,
+            map_grouped: false,
+            connections: Vec::new(),
+            logs,
+            log_path,
+            show_debug: false,
+            apply_time: Duration::ZERO,
+            draw_time: Duration::ZERO,
+            visible_pids: HashSet::new(),
+            memory_of_all: false,
+            view_scroll: 0,
+            view_selected: 0,
         })
     }
 
-    fn update(&mut self) {
-        self.system_info.update();
-        self.process_list.update();
+    fn settings(&self) -> Settings {
+        Settings {
+            delay: self.collector.delay().as_millis() as u64,
+            sort: (self.process_list.sort_field(), self.process_list.sort_order()),
+            tree: self.process_list.tree(),
+            show_threads: self.process_list.show_threads(),
+            hide_kernel_threads: self.process_list.hide_kernel_threads(),
+            filter: self.input_state.search_input.clone(),
+            theme: self.theme_name.clone(),
+        }
+    }
+
+    // Switches to the theme after the current one, including the user's theme files
+    fn next_theme(&mut self) {
+        let dir = self.config.themes_dir();
+        let names = ui::theme_names(dir.as_deref());
+        let index = names.iter().position(|name| *name == self.theme_name).map_or(0, |i| i + 1);
+        let name = names[index % names.len()].clone();
+        match Theme::load(&name, dir.as_deref()) {
+            Ok(theme) => {
+                self.theme = theme.downgrade(self.color_depth);
+                self.message = Some(format!("Theme: {}", name));
+                self.theme_name = name;
+            }
+            Err(err) => self.message = Some(format!("Cannot load theme {}: {:#}", name, err)),
+        }
+    }
+
+    // Writes the settings changed since the last call to the config file; values that
+    // only came from the command line stay out of it until they are changed in the UI
+    fn save_settings(&mut self) {
+        if self.input_state.mode == InputMode::Search {
+            return;
+        }
+        let current = self.settings();
+        if current == self.settings {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.settings, current.clone());
+        let config = &mut self.config.config;
+        if current.delay != previous.delay {
+            config.delay = current.delay;
+        }
+        if current.sort != previous.sort {
+            config.sort_key = Some(current.sort.0.into());
+            config.sort_order = Some(current.sort.1.into());
+        }
+        if current.tree != previous.tree {
+            config.tree = current.tree;
+        }
+        if current.show_threads != previous.show_threads {
+            config.show_threads = current.show_threads;
+        }
+        if current.hide_kernel_threads != previous.hide_kernel_threads {
+            config.hide_kernel_threads = current.hide_kernel_threads;
+        }
+        if current.filter != previous.filter {
+            config.filter = current.filter;
+        }
+        if current.theme != previous.theme {
+            config.theme = current.theme;
+        }
+
+        if let Err(err) = self.config.save() {
+            self.message = Some(format!("Cannot save config: {:#}", err));
+        }
+    }
+
+    // Tells the collector which processes are on screen and whether the sort needs the
+    // smaps memory of all of them, so it reads the costlier fields only where they show
+    fn sync_collector(&mut self) {
+        let rows = self.process_list.processes();
+        let range = self.regions.visible_rows();
+        let visible: HashSet<Pid> = rows[range.start.min(rows.len())..range.end.min(rows.len())]
+            .iter()
+            .map(|process| process.pid)
+            .collect();
+        if visible != self.visible_pids {
+            self.collector.visible(visible.clone());
+            self.visible_pids = visible;
+        }
+
+        let memory_of_all = matches!(self.process_list.sort_field(), SortField::Memory(kind) if kind != MemoryKind::Rss);
+        if memory_of_all != self.memory_of_all {
+            self.collector.memory_of_all(memory_of_all);
+            self.memory_of_all = memory_of_all;
+        }
+    }
+
+    // Takes in a snapshot published by the collector thread
+    fn apply(&mut self, snapshot: Arc<Snapshot>) {
+        let started = Instant::now();
+        // While paused only the open view follows the collector, so it can still be inspected
+        let snapshot = if self.paused {
+            Arc::new(Snapshot { view: Arc::clone(&snapshot.view), ..Snapshot::clone(&self.snapshot) })
+        } else {
+            snapshot
+        };
+        // Sources that were not due come back unchanged, shared with the previous snapshot
+        let same_processes = Arc::ptr_eq(&snapshot.processes, &self.snapshot.processes)
+            && Arc::ptr_eq(&snapshot.threads, &self.snapshot.threads);
+        // Raw readings differ nearly every time; only what the meters show counts
+        let meters_changed =
+            ui::meters_shown(&self.meters, &snapshot.system) != ui::meters_shown(&self.meters, &self.snapshot.system);
+        let changed = meters_changed
+            | (!same_processes && self.process_list.apply(&snapshot.processes, &snapshot.threads));
+        self.snapshot = snapshot;
+        self.refresh_view();
+        self.apply_time = started.elapsed();
+        // Secondary views are cheap to draw and refresh their own data
+        self.dirty |= changed || self.view != View::Processes || self.show_debug;
+    }
+
+    fn refresh_view(&mut self) {
+        let snapshot = Arc::clone(&self.snapshot);
+        // Data of another view, collected before the collector learned about this one, is ignored
+        match (self.view, &*snapshot.view) {
+            (View::Detail(pid), ViewData::Detail(of, detail)) if pid == *of => self.detail = detail.as_deref().cloned(),
+            (View::Files(pid), ViewData::Files(of, files)) if pid == *of => {
+                log_read_error(&self.files, files);
+                self.files = files.clone();
+            }
+            (View::MemoryMap(pid), ViewData::MemoryMap(of, maps)) if pid == *of => {
+                log_read_error(&self.maps, maps);
+                self.maps = maps.clone();
+            }
+            (View::Network, ViewData::Network(connections)) => {
+                self.connections = connections.clone();
+                let visible = self.visible_connections().len();
+                self.view_selected = self.view_selected.min(visible.saturating_sub(1));
+            }
+            _ => {}
+        }
+    }
+
+    // Steps through DELAY_STEPS from the current delay, which may lie between two of them
+    fn change_delay(&mut self, longer: bool) {
+        let current = self.collector.delay().as_millis() as u64;
+        let next = if longer {
+            DELAY_STEPS.iter().find(|ms| **ms > current)
+        } else {
+            DELAY_STEPS.iter().rev().find(|ms| **ms < current)
+        };
+        let delay = next.copied().unwrap_or(current);
+        self.collector.set_delay(Duration::from_millis(delay));
+        self.message = Some(format!("Delay: {} ms", delay));
+    }
+
+    // Writes the listed rows, as filtered and sorted, to a file in the current directory
+    fn export(&mut self) {
+        self.message = Some(match export::to_file(&self.snapshot, self.process_list.processes()) {
+            Ok(path) => format!("Exported to {}", path.display()),
+            Err(err) => format!("Cannot export: {}", err),
+        });
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.message = Some(if self.paused { "Paused" } else { "Resumed" }.to_string());
+    }
+
+    // The view's data arrives with the next snapshot, which the collector takes right away
+    fn open_view(&mut self, view: View) {
+        self.view = view;
+        self.view_scroll = 0;
+        self.view_selected = 0;
+        self.collector.watch(view.watched());
+    }
+
+    fn killed(&mut self, pid: Pid, result: Result<(), String>) {
+        match result {
+            Ok(()) => tracing::info!("killed process {}", pid),
+            Err(err) => {
+                tracing::warn!("cannot kill process {}: {}", pid, err);
+                self.message = Some(format!("Cannot kill {}: {}", pid, err));
+                self.dirty = true;
+            }
+        }
+    }
+
+    // Connections matching the search query of the network screen
+    fn visible_connections(&self) -> Vec<&Connection> {
+        let query = self.view_query.to_lowercase();
+        self.connections
+            .iter()
+            .filter(|connection| {
+                if query.is_empty() {
+                    return true;
+                }
+                let name = connection
+                    .pid
+                    .and_then(|pid| self.process_list.name_of(Pid::from_u32(pid)))
+                    .unwrap_or("");
+                connection.socket.to_string().to_lowercase().contains(&query)
+                    || name.to_lowercase().contains(&query)
+                    || connection.pid.is_some_and(|pid| pid.to_string() == query)
+            })
+            .collect()
     }
 
     fn handle_input(&mut self, key: KeyEvent) {
+        // Messages stay on the status bar until the next key press
+        self.message = None;
+        self.dirty = true;
+
+        if self.view != View::Processes {
+            self.handle_view_input(key);
+            return;
+        }
+
         if self.input_state.handle_input(key) {
-            if !self.input_state.search_input.is_empty() {
-                self.process_list.filter(&self.input_state.search_input);
-            }
+            self.process_list.filter(&self.input_state.search_input);
+            return;
+        }
+
+        if let Some(action) = self.keymap.handle(key) {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        if self.view != View::Processes {
+            self.perform_in_view(action);
             return;
         }
 
-        match key.code {
+        match action {
             // Quit
-            KeyCode::Char('q') | KeyCode::Char('Q') => {
+            Action::Quit => {
                 self.should_quit = true;
             }
 
             // Search
-            KeyCode::F(3) => {
+            Action::Search => {
                 self.input_state.enter_search_mode();
             }
 
             // Navigation
-            KeyCode::Char('j') | KeyCode::Down => self.process_list.move_selection(1),
-            KeyCode::Char('k') | KeyCode::Up => self.process_list.move_selection(-1),
-            KeyCode::PageDown => self.process_list.move_selection(10),
-            KeyCode::PageUp => self.process_list.move_selection(-10),
-            KeyCode::Home => self.process_list.move_to_start(),
-            KeyCode::End => self.process_list.move_to_end(),
+            Action::Down => self.process_list.move_selection(1),
+            Action::Up => self.process_list.move_selection(-1),
+            Action::PageDown => self.process_list.move_selection(10),
+            Action::PageUp => self.process_list.move_selection(-10),
+            Action::Home => self.process_list.move_to_start(),
+            Action::End => self.process_list.move_to_end(),
 
             // Sorting
-            KeyCode::Char('p') | KeyCode::Char('P') => self.process_list.set_sort_field(SortField::Pid),
-            KeyCode::Char('n') | KeyCode::Char('N') => self.process_list.set_sort_field(SortField::Name),
-            KeyCode::Char('c') | KeyCode::Char('C') => self.process_list.set_sort_field(SortField::Cpu),
-            KeyCode::Char('m') | KeyCode::Char('M') => self.process_list.set_sort_field(SortField::Memory),
+            Action::SortPid => self.process_list.set_sort_field(SortField::Pid),
+            Action::SortName => self.process_list.set_sort_field(SortField::Name),
+            Action::SortCpu => self.process_list.set_sort_field(SortField::Cpu),
+            Action::SortMemory => self.process_list.cycle_memory_sort(),
+            Action::InvertSort => self.process_list.invert_sort_order(),
+
+            // Tree view
+            Action::ToggleTree => self.process_list.set_tree(!self.process_list.tree()),
+
+            // Themes
+            Action::NextTheme => self.next_theme(),
+
+            // Threads
+            Action::ToggleThreads => {
+                self.process_list.toggle_threads();
+                self.collector.show_threads(self.process_list.show_threads());
+            }
+            Action::ToggleKernelThreads => self.process_list.toggle_kernel_threads(),
+
+            // Process details
+            Action::Details => {
+                if let Some(pid) = self.process_list.selected_pid() {
+                    self.detail_tab = DetailTab::Overview;
+                    self.open_view(View::Detail(pid));
+                }
+            }
+
+            // Environment
+            Action::Environment => {
+                if let Some(pid) = self.process_list.selected_pid() {
+                    self.detail_tab = DetailTab::Environment;
+                    self.open_view(View::Detail(pid));
+                }
+            }
+
+            // Open files
+            Action::Files => {
+                if let Some(pid) = self.process_list.selected_pid() {
+                    self.open_view(View::Files(pid));
+                }
+            }
+
+            // Memory map
+            Action::MemoryMap => {
+                if let Some(pid) = self.process_list.selected_pid() {
+                    self.open_view(View::MemoryMap(pid));
+                }
+            }
+
+            // Listening ports and connections
+            Action::Network => self.open_view(View::Network),
+            Action::Log => self.open_view(View::Log),
 
             // Kill process
-            KeyCode::F(9) => {
+            Action::Kill if self.read_only => {
+                self.message = Some("Read-only mode: killing processes is disabled".to_string());
+            }
+            Action::Kill => {
                 if let Some(pid) = self.process_list.selected_pid() {
-                    self.process_list.kill_process(pid);
+                    self.collector.kill(pid);
                 }
             }
 
+            // Refresh rate
+            Action::IncreaseDelay => self.change_delay(true),
+            Action::DecreaseDelay => self.change_delay(false),
+            Action::Pause => self.toggle_pause(),
+
+            Action::Export => self.export(),
+
+            // Suspend
+            Action::Suspend => {
+                self.should_suspend = true;
+            }
+
+            // Collection and drawing times
+            Action::Debug => {
+                self.show_debug = !self.show_debug;
+            }
+
             // Help
-            KeyCode::F(1) => {
+            Action::Help => {
                 // TODO: Show help screen
             }
 
+            // Keys of the views
+            Action::CloseView
+            | Action::NextTab
+            | Action::ViewSearch
+            | Action::RevealSecrets
+            | Action::SortMappings
+            | Action::GroupMappings => {}
+        }
+    }
+
+    fn move_connection(&mut self, delta: i32) {
+        let last = self.visible_connections().len().saturating_sub(1) as i32;
+        self.view_selected = (self.view_selected as i32 + delta).clamp(0, last) as usize;
+    }
+
+    fn handle_view_input(&mut self, key: KeyEvent) {
+        if self.view_searching {
+            match key.code {
+                KeyCode::Esc => {
+                    self.view_searching = false;
+                    self.view_query.clear();
+                }
+                KeyCode::Enter => self.view_searching = false,
+                KeyCode::Backspace => {
+                    self.view_query.pop();
+                }
+                KeyCode::Char(c) => self.view_query.push(c),
+                _ => {}
+            }
+            self.view_scroll = 0;
+            self.view_selected = 0;
+            return;
+        }
+
+        if let Some(action) = self.keymap.handle(key) {
+            self.perform_in_view(action);
+        }
+    }
+
+    // Jumps from the selected connection to its process in the main list
+    fn jump_to_connection(&mut self) {
+        let pid = self
+            .visible_connections()
+            .get(self.view_selected)
+            .and_then(|connection| connection.pid);
+        let Some(pid) = pid else {
+            return;
+        };
+        let Some(cleared) = self.process_list.select_pid(Pid::from_u32(pid)) else {
+            self.message = Some(if self.process_list.restricted() {
+                format!("Process {} is not listed; -p and -u limit the list", pid)
+            } else {
+                format!("Process {} is not listed", pid)
+            });
+            return;
+        };
+        self.close_view();
+        // The query shown in the search box must not come back with the next edit
+        if cleared.filter {
+            self.input_state.search_input.clear();
+        }
+        let reset = match (cleared.filter, cleared.kernel_threads) {
+            (true, true) => Some("Cleared the search and showed kernel threads"),
+            (true, false) => Some("Cleared the search"),
+            (false, true) => Some("Showed kernel threads"),
+            (false, false) => None,
+        };
+        if let Some(reset) = reset {
+            self.message = Some(format!("{} to select process {}", reset, pid));
+        }
+    }
+
+    fn perform_in_view(&mut self, action: Action) {
+        let network = self.view == View::Network;
+        let detail = matches!(self.view, View::Detail(_));
+        let environment = detail && self.detail_tab == DetailTab::Environment;
+        let memory_map = matches!(self.view, View::MemoryMap(_));
+        match action {
+            Action::Quit | Action::CloseView => self.close_view(),
+            Action::NextTab if detail => {
+                self.detail_tab = match self.detail_tab {
+                    DetailTab::Overview => DetailTab::Environment,
+                    DetailTab::Environment => DetailTab::Overview,
+                };
+                self.view_scroll = 0;
+            }
+            Action::ViewSearch if environment || network => self.view_searching = true,
+            Action::RevealSecrets if environment => self.reveal_secrets = !self.reveal_secrets,
+            Action::Details if network => self.jump_to_connection(),
+            Action::SortMappings if memory_map => self.map_sort = self.map_sort.next(),
+            Action::GroupMappings if memory_map => {
+                self.map_grouped = !self.map_grouped;
+                self.view_scroll = 0;
+            }
+            Action::Down if network => self.move_connection(1),
+            Action::Up if network => self.move_connection(-1),
+            Action::PageDown if network => self.move_connection(10),
+            Action::PageUp if network => self.move_connection(-10),
+            Action::Home if network => self.view_selected = 0,
+            Action::Down => self.view_scroll = self.view_scroll.saturating_add(1),
+            Action::Up => self.view_scroll = self.view_scroll.saturating_sub(1),
+            Action::PageDown => self.view_scroll = self.view_scroll.saturating_add(10),
+            Action::PageUp => self.view_scroll = self.view_scroll.saturating_sub(10),
+            Action::Home => self.view_scroll = 0,
+            Action::IncreaseDelay => self.change_delay(true),
+            Action::DecreaseDelay => self.change_delay(false),
+            Action::Pause => self.toggle_pause(),
+            Action::Suspend => self.should_suspend = true,
+            Action::Debug => self.show_debug = !self.show_debug,
             _ => {}
         }
     }
+
+    // Pasted text only goes into a search field, as a single line
+    fn handle_paste(&mut self, text: &str) {
+        let line: String = text.lines().next().unwrap_or_default().chars().filter(|c| !c.is_control()).collect();
+        if self.view_searching {
+            self.view_query.push_str(&line);
+            self.view_scroll = 0;
+            self.view_selected = 0;
+        } else if self.view == View::Processes && self.input_state.mode == InputMode::Search {
+            self.input_state.search_input.push_str(&line);
+            self.process_list.filter(&self.input_state.search_input);
+        } else {
+            return;
+        }
+        self.dirty = true;
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (x, y) = (mouse.column, mouse.row);
+        // Motion events arrive constantly and change nothing
+        if matches!(mouse.kind, MouseEventKind::Moved | MouseEventKind::Drag(_) | MouseEventKind::Up(_)) {
+            return;
+        }
+        self.dirty = true;
+        match mouse.kind {
+            MouseEventKind::ScrollDown => (0..WHEEL_LINES).for_each(|_| self.perform(Action::Down)),
+            MouseEventKind::ScrollUp => (0..WHEEL_LINES).for_each(|_| self.perform(Action::Up)),
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.message = None;
+                if let Some(action) = self.regions.button_at(x, y) {
+                    self.perform(action);
+                    return;
+                }
+                if self.view != View::Processes {
+                    return;
+                }
+                if let Some(column) = self.regions.column_at(x, y) {
+                    // Clicking the sort column again flips the direction, like htop
+                    match column.sort_field() {
+                        Some(field) if field == self.process_list.sort_field() => {
+                            self.process_list.invert_sort_order()
+                        }
+                        Some(field) => self.process_list.set_sort_field(field),
+                        None => {}
+                    }
+                } else if let Some(index) = self.regions.row_at(x, y) {
+                    self.process_list.select_index(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn close_view(&mut self) {
+        self.view = View::Processes;
+        self.collector.watch(self.view.watched());
+        self.detail = None;
+        self.files = Ok(Vec::new());
+        self.maps = Ok(Vec::new());
+        self.connections.clear();
+        // Secrets are masked again whenever the panel is left
+        self.reveal_secrets = false;
+        self.view_query.clear();
+    }
+}
+
+// Logs a failure to read /proc when it first shows up, not on every refresh
+fn log_read_error<T>(previous: &Result<T, String>, current: &Result<T, String>) {
+    if let Err(err) = current {
+        if previous.as_ref().err() != Some(err) {
+            tracing::warn!("{}", err);
+        }
+    }
+}
+
+// Sort, restrictions, tree and filter from the options, falling back to the config file
+fn configured_process_list(options: &Options, saved: &Config) -> ProcessList {
+    let mut process_list = ProcessList::new();
+    if let Some(key) = options.sort_key.or(saved.sort_key) {
+        process_list.set_sort_field(key.into());
+    }
+    if let Some(order) = options.sort_order.or(saved.sort_order) {
+        process_list.set_sort_order(order.into());
+    }
+    let users = if options.users.is_empty() { &saved.users } else { &options.users };
+    process_list.restrict(
+        options.pids.iter().map(|pid| Pid::from_u32(*pid)).collect(),
+        users.clone(),
+    );
+    process_list.set_tree(options.tree || saved.tree);
+    if saved.show_threads {
+        process_list.toggle_threads();
+    }
+    if saved.hide_kernel_threads {
+        process_list.toggle_kernel_threads();
+    }
+    let filter = options.filter.as_ref().unwrap_or(&saved.filter);
+    if !filter.is_empty() {
+        process_list.filter(filter);
+    }
+    process_list
+}
+
+fn open_source(options: &Options) -> Result<Box<dyn Source>> {
+    let kind = match (options.source, &options.proc_root) {
+        (Some(kind), None) => kind,
+        (None | Some(SourceKind::Procfs), Some(root)) => {
+            if !root.join("stat").is_file() {
+                bail!("{} is not a proc filesystem", root.display());
+            }
+            SourceKind::Procfs
+        }
+        (Some(_), Some(_)) => bail!("--proc-root only applies to the procfs source"),
+        (None, None) => SourceKind::Sysinfo,
+    };
+    Ok(match kind {
+        SourceKind::Sysinfo => Box::new(SysinfoSource::new()),
+        SourceKind::Procfs => {
+            let root = options.proc_root.clone().unwrap_or_else(|| PathBuf::from(procfs::PROC_ROOT));
+            Box::new(ProcfsSource::new(root))
+        }
+        SourceKind::Demo => Box::new(ScriptedSource::demo()),
+    })
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+async fn main() -> Result<ExitCode> {
+    let options = Options::parse();
 
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    let config = ConfigFile::load(options.config.clone().or_else(config::default_path));
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // Logs go to a file and the log view, anything written to the terminal would garble the screen
+    let log_path = options.log_file.clone().or_else(|| config.config.log_file.clone()).or_else(logging::default_path);
+    let (logging, log_warning) = logging::init(options.log_level.as_deref(), log_path)?;
 
-    // Create app state
-    let mut app = App::new()?;
-    
-    // Create event handler
-    let event_handler = EventHandler::new(Duration::from_millis(250));
+    // Create app state before touching the terminal so option errors print cleanly
+    batch::check(&options)?;
+    let source = open_source(&options)?;
+    let delay = Duration::from_millis(options.delay.unwrap_or(config.config.delay));
+    let (collector, updates) = Collector::spawn(source, delay, config.config.intervals, config.config.show_threads)?;
+    if options.batch || options.output.is_some() {
+        if let Some(warning) = log_warning {
+            tracing::warn!("{}", warning);
+            eprintln!("Log: {}", warning);
+        }
+        batch::run(&options, config, collector, updates).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut app = App::new(&options, config, collector, logging.buffer.clone(), logging.path.clone())?;
+    if let Some(warning) = log_warning {
+        tracing::warn!("{}", warning);
+        app.message.get_or_insert(format!("Log: {}", warning));
+    }
 
-    // Run app
-    let res = run_app(&mut terminal, &mut app, &event_handler).await;
+    // Put the terminal back before the panic message is printed, or it is lost
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        tracing::error!("{}", info);
+        default_hook(info);
+    }));
 
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
+    // Setup terminal
+    if let Err(err) = setup_terminal() {
+        restore_terminal();
+        return Err(err);
+    }
+    let backend = CrosstermBackend::new(io::stdout());
+    let res = match Terminal::new(backend) {
+        Ok(mut terminal) => match EventHandler::new(updates) {
+            // Dropping the handler afterwards stops the timers, the input stream and signal handling
+            Ok(mut event_handler) => run_app(&mut terminal, &mut app, &mut event_handler).await,
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err.into()),
+    };
 
-    if let Err(err) = res {
-        println!("{:?}", err)
+    // Restore terminal; errors are reported after leaving the alternate screen
+    restore_terminal();
+    if let Err(err) = &res {
+        tracing::error!("{:#}", err);
     }
+    res
+}
 
+// Raw mode, the alternate screen and the extra input reporting
+fn setup_terminal() -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // Not every terminal supports these; they only add events
+    let _ = execute!(stdout, EnableFocusChange, EnableBracketedPaste);
     Ok(())
 }
 
+// Undoes setup_terminal; also runs from the panic hook, so it never fails
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    let _ = execute!(stdout, DisableFocusChange, DisableBracketedPaste);
+    let _ = execute!(stdout, LeaveAlternateScreen, DisableMouseCapture, Show);
+}
+
+// Hands the terminal back to the shell until the process is continued
+#[cfg(unix)]
+fn suspend<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>) -> Result<()> {
+    restore_terminal();
+    // SIGTSTP would only reach our own handler again, SIGSTOP cannot be caught
+    signal_hook::low_level::raise(signal_hook::consts::SIGSTOP)?;
+    setup_terminal()?;
+    terminal.clear()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn suspend<B: ratatui::backend::Backend>(_terminal: &mut Terminal<B>) -> Result<()> {
+    anyhow::bail!("not supported on this platform")
+}
+
+// Exits with 0 on quit, or 128 plus the signal number when terminated by a signal
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    event_handler: &EventHandler,
-) -> Result<()> {
-    loop {
-        terminal.draw(|f| ui::draw(f, app))?;
+    event_handler: &mut EventHandler,
+) -> Result<ExitCode> {
+    let mut status = ExitCode::SUCCESS;
+    let mut rows = RowCache::default();
 
-        match event_handler.next()? {
-            event::Event::Input(key) => {
-                app.handle_input(key);
+    // Input only marks the screen dirty; the frame timer batches the redraws and
+    // the tick timer refreshes the data, so neither waits on the other
+    while !app.should_quit {
+        match event_handler.next(app.dirty).await? {
+            event::Event::Frame => {
+                let started = Instant::now();
+                terminal.draw(|f| app.regions = ui::draw(f, app, &mut rows))?;
+                app.draw_time = started.elapsed();
+                // Kept while a secondary view is open, so the list comes back scrolled as it was
+                if let Some(offset) = app.regions.offset() {
+                    app.list_offset = offset;
+                }
+                app.dirty = false;
+                app.sync_collector();
+            }
+            event::Event::Collector(Update::Snapshot(snapshot)) => app.apply(snapshot),
+            event::Event::Collector(Update::Killed(pid, result)) => app.killed(pid, result),
+            event::Event::Input(key) => app.handle_input(key),
+            event::Event::Mouse(mouse) => app.handle_mouse(mouse),
+            // The next draw picks up the new size
+            event::Event::Resize => app.dirty = true,
+            // Some terminals lose the screen contents while in the background
+            event::Event::FocusGained => {
+                terminal.clear()?;
+                app.dirty = true;
             }
-            event::Event::Tick => {
-                app.update();
+            event::Event::FocusLost => app.keymap.reset(),
+            event::Event::Paste(text) => app.handle_paste(&text),
+            event::Event::Terminate(signal) => {
+                app.should_quit = true;
+                status = ExitCode::from(128 + signal as u8);
+            }
+            event::Event::Suspend => app.should_suspend = true,
+            // Stopped from outside; the shell may have reset the terminal meanwhile
+            event::Event::Resume => {
+                setup_terminal()?;
+                terminal.clear()?;
+                app.dirty = true;
             }
         }
 
-        if app.should_quit {
-            return Ok(());
+        if app.should_suspend {
+            app.should_suspend = false;
+            app.keymap.reset();
+            if let Err(err) = suspend(terminal) {
+                app.message = Some(format!("Cannot suspend: {:#}", err));
+            }
+            app.dirty = true;
         }
+        app.save_settings();
     }
+    Ok(status)
 }
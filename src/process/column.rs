@@ -0,0 +1,211 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : process/column.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Process table columns and memory accounting kinds
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use super::{Process, SortField};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryKind {
+    Rss,
+    Pss,
+    Uss,
+    Shared,
+    Swap,
+}
+
+impl MemoryKind {
+    pub fn next(self) -> Self {
+        match self {
+            MemoryKind::Rss => MemoryKind::Pss,
+            MemoryKind::Pss => MemoryKind::Uss,
+            MemoryKind::Uss => MemoryKind::Shared,
+            MemoryKind::Shared => MemoryKind::Swap,
+            MemoryKind::Swap => MemoryKind::Rss,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MemoryKind::Rss => "RSS",
+            MemoryKind::Pss => "PSS",
+            MemoryKind::Uss => "USS",
+            MemoryKind::Shared => "SHR",
+            MemoryKind::Swap => "SWAP",
+        }
+    }
+
+    // RSS is always known from sysinfo; the others need a readable smaps_rollup
+    pub fn value(self, process: &Process) -> Option<u64> {
+        match self {
+            MemoryKind::Rss => Some(process.memory.map_or(process.memory_usage, |m| m.rss)),
+            MemoryKind::Pss => process.memory.map(|m| m.pss),
+            MemoryKind::Uss => process.memory.map(|m| m.uss),
+            MemoryKind::Shared => process.memory.map(|m| m.shared),
+            MemoryKind::Swap => process.memory.map(|m| m.swap),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    Pid,
+    User,
+    State,
+    LastCpu,
+    Cpu,
+    Memory(MemoryKind),
+    Name,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 11] = [
+        Column::Pid,
+        Column::User,
+        Column::State,
+        Column::LastCpu,
+        Column::Cpu,
+        Column::Memory(MemoryKind::Rss),
+        Column::Memory(MemoryKind::Pss),
+        Column::Memory(MemoryKind::Uss),
+        Column::Memory(MemoryKind::Shared),
+        Column::Memory(MemoryKind::Swap),
+        Column::Name,
+    ];
+
+    // Identifier used in the configuration file
+    pub fn name(self) -> &'static str {
+        match self {
+            Column::Pid => "pid",
+            Column::User => "user",
+            Column::State => "state",
+            Column::LastCpu => "last_cpu",
+            Column::Cpu => "cpu",
+            Column::Memory(MemoryKind::Rss) => "rss",
+            Column::Memory(MemoryKind::Pss) => "pss",
+            Column::Memory(MemoryKind::Uss) => "uss",
+            Column::Memory(MemoryKind::Shared) => "shared",
+            Column::Memory(MemoryKind::Swap) => "swap",
+            Column::Name => "name",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::DEFAULT.into_iter().find(|column| column.name() == name)
+    }
+
+    pub fn sort_field(self) -> Option<SortField> {
+        match self {
+            Column::Pid => Some(SortField::Pid),
+            Column::Cpu => Some(SortField::Cpu),
+            Column::Memory(kind) => Some(SortField::Memory(kind)),
+            Column::Name => Some(SortField::Name),
+            Column::User | Column::State | Column::LastCpu => None,
+        }
+    }
+
+    pub fn header(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::User => "USER",
+            Column::State => "S",
+            Column::LastCpu => "CPU",
+            Column::Cpu => "CPU%",
+            Column::Memory(kind) => kind.label(),
+            Column::Name => "NAME",
+        }
+    }
+
+    // Fixed width of the column; the name column takes the remaining space
+    pub fn width(self) -> usize {
+        match self {
+            Column::Pid => 7,
+            Column::User => 9,
+            Column::State => 1,
+            Column::LastCpu => 3,
+            Column::Cpu => 6,
+            Column::Memory(_) => 7,
+            Column::Name => 0,
+        }
+    }
+
+    pub fn value(self, process: &Process) -> String {
+        match self {
+            Column::Pid => process.pid.to_string(),
+            Column::User => match (&process.user, &process.uid) {
+                (Some(user), _) => user.chars().take(self.width()).collect(),
+                (None, Some(uid)) => (**uid).to_string(),
+                (None, None) => "-".to_string(),
+            },
+            Column::State => process.state.to_string(),
+            Column::LastCpu => process.last_cpu.map_or_else(|| "-".to_string(), |cpu| cpu.to_string()),
+            Column::Cpu => format!("{:.1}", process.cpu_usage),
+            Column::Memory(kind) => kind.value(process).map_or_else(|| "-".to_string(), format_bytes),
+            // Children and threads are indented beneath the process that owns them
+            Column::Name if process.depth == 0 => process.name.clone(),
+            Column::Name => format!("{}└─ {}", " ".repeat(process.depth * 2 - 1), process.name),
+        }
+    }
+
+    // Unformatted value for machine-readable output; empty when unknown
+    pub fn raw_value(self, process: &Process) -> String {
+        match self {
+            Column::Pid => process.pid.to_string(),
+            Column::User => match (&process.user, &process.uid) {
+                (Some(user), _) => user.clone(),
+                (None, Some(uid)) => (**uid).to_string(),
+                (None, None) => String::new(),
+            },
+            Column::State => process.state.to_string(),
+            Column::LastCpu => process.last_cpu.map_or_else(String::new, |cpu| cpu.to_string()),
+            Column::Cpu => format!("{:.1}", process.cpu_usage),
+            Column::Memory(kind) => kind.value(process).map_or_else(String::new, |bytes| bytes.to_string()),
+            Column::Name => process.name.clone(),
+        }
+    }
+
+    // Formats one cell, right-aligning numbers and left-aligning text
+    pub fn cell(self, value: &str) -> String {
+        match self {
+            Column::Name => value.to_string(),
+            Column::User | Column::State => format!("{:<width$}", value, width = self.width()),
+            _ => format!("{:>width$}", value, width = self.width()),
+        }
+    }
+}
+
+// One process as a line of text, its cells separated by a space
+pub fn format_row(process: &Process, columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|column| column.cell(&column.value(process)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}B", bytes)
+    } else {
+        format!("{:.1}{}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,127 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : process/detail.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Full metadata of a single process for the detail panel
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::path::{Path, PathBuf};
+use sysinfo::{Pid, System, Process as SysProcess, Users};
+use serde::{Deserialize, Serialize};
+use crate::{ids, procfs::{self, Credentials}};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessDetail {
+    #[serde(deserialize_with = "ids::id")]
+    pub pid: Pid,
+    pub name: String,
+    pub exe: Option<PathBuf>,
+    pub cwd: Option<PathBuf>,
+    pub root: Option<PathBuf>,
+    pub cmdline: Vec<String>,
+    pub environ: Vec<String>,
+    pub user: Option<String>,
+    pub credentials: Option<Credentials>,
+    #[serde(deserialize_with = "ids::named_ids")]
+    pub parents: Vec<(Pid, String)>,
+    pub start_time: u64,
+    pub run_time: u64,
+    pub state: String,
+    pub priority: Option<i64>,
+    pub nice: Option<i64>,
+    pub cgroups: Vec<String>,
+    pub namespaces: Vec<(String, String)>,
+    pub limits: Vec<String>,
+}
+
+impl ProcessDetail {
+    pub fn new(pid: Pid, process: &SysProcess, system: &System, users: &Users) -> Self {
+        // Walk up the parent chain, guarding against cycles from pid reuse
+        let mut parents = Vec::new();
+        let mut current = process.parent();
+        while let Some(parent_pid) = current {
+            if parent_pid == pid || parents.iter().any(|(p, _)| *p == parent_pid) {
+                break;
+            }
+            let Some(parent) = system.process(parent_pid) else {
+                break;
+            };
+            parents.push((parent_pid, parent.name().to_string()));
+            current = parent.parent();
+        }
+
+        let dir = procfs::pid_path(Path::new(procfs::PROC_ROOT), pid.as_u32());
+        let stat = procfs::read_stat(&dir);
+
+        Self {
+            pid,
+            name: process.name().to_string(),
+            exe: process.exe().map(PathBuf::from),
+            cwd: process.cwd().map(PathBuf::from),
+            root: process.root().map(PathBuf::from),
+            cmdline: process.cmd().to_vec(),
+            environ: process.environ().to_vec(),
+            user: process
+                .user_id()
+                .and_then(|uid| users.get_user_by_id(uid))
+                .map(|user| user.name().to_string()),
+            credentials: procfs::read_credentials(&dir),
+            parents,
+            start_time: process.start_time(),
+            run_time: process.run_time(),
+            state: process.status().to_string(),
+            priority: stat.as_ref().map(|s| s.priority),
+            nice: stat.as_ref().map(|s| s.nice),
+            cgroups: procfs::read_cgroups(&dir),
+            namespaces: procfs::read_namespaces(&dir),
+            limits: procfs::read_limits(&dir),
+        }
+    }
+}
+
+// Formats seconds since the Unix epoch as a UTC date and time
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+pub fn format_duration(secs: u64) -> String {
+    let days = secs / 86_400;
+    let clock = format!("{:02}:{:02}:{:02}", secs % 86_400 / 3_600, secs % 3_600 / 60, secs % 60);
+    if days > 0 {
+        format!("{}d {}", days, clock)
+    } else {
+        clock
+    }
+}
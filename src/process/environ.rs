@@ -0,0 +1,169 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : process/environ.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Environment variables of a process with secret redaction
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::env;
+
+const DEFAULT_SECRET_PATTERNS: [&str; 4] = ["TOKEN", "PASSWORD", "KEY", "SECRET"];
+
+// Extra patterns, comma separated, on top of the defaults
+const SECRET_PATTERNS_VAR: &str = "BLACKWIN_HTOP_SECRET_PATTERNS";
+
+const MASK: &str = "********";
+
+#[derive(Debug, Clone)]
+pub struct SecretPatterns {
+    patterns: Vec<String>,
+}
+
+impl SecretPatterns {
+    pub fn new<I: IntoIterator<Item = String>>(patterns: I) -> Self {
+        Self {
+            patterns: patterns
+                .into_iter()
+                .map(|pattern| pattern.trim().to_uppercase())
+                .filter(|pattern| !pattern.is_empty())
+                .collect(),
+        }
+    }
+
+    // The defaults, the patterns from the environment and the configured ones
+    pub fn from_env(configured: &[String]) -> Self {
+        let extra = env::var(SECRET_PATTERNS_VAR).unwrap_or_default();
+        Self::new(
+            DEFAULT_SECRET_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .chain(extra.split(',').map(str::to_string))
+                .chain(configured.iter().cloned()),
+        )
+    }
+
+    // Case-insensitive substring match on the variable name
+    pub fn is_secret(&self, key: &str) -> bool {
+        let key = key.to_uppercase();
+        self.patterns.iter().any(|pattern| key.contains(pattern.as_str()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvVar {
+    pub key: String,
+    pub value: String,
+    pub secret: bool,
+}
+
+impl EnvVar {
+    pub fn display_value(&self, reveal: bool) -> &str {
+        if self.secret && !reveal {
+            MASK
+        } else {
+            &self.value
+        }
+    }
+}
+
+// Splits raw "KEY=value" entries, sorts them by key and keeps those matching `query`
+pub fn environment(environ: &[String], patterns: &SecretPatterns, query: &str) -> Vec<EnvVar> {
+    let query = query.to_lowercase();
+    let mut vars: Vec<EnvVar> = environ
+        .iter()
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key, value) = entry.split_once('=').unwrap_or((entry.as_str(), ""));
+            EnvVar {
+                key: key.to_string(),
+                value: value.to_string(),
+                secret: patterns.is_secret(key),
+            }
+        })
+        // Secret values never take part in the search so masked rows cannot be probed
+        .filter(|var| {
+            query.is_empty()
+                || var.key.to_lowercase().contains(&query)
+                || (!var.secret && var.value.to_lowercase().contains(&query))
+        })
+        .collect();
+    vars.sort_by(|a, b| a.key.cmp(&b.key));
+    vars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(vars: &[EnvVar]) -> Vec<&str> {
+        vars.iter().map(|var| var.key.as_str()).collect()
+    }
+
+    #[test]
+    fn default_patterns_ignore_case() {
+        let patterns = SecretPatterns::from_env(&[]);
+        for key in ["GITHUB_TOKEN", "db_password", "Api_Key", "SSH_KEY_PATH", "secret"] {
+            assert!(patterns.is_secret(key), "{}", key);
+        }
+        for key in ["PATH", "HOME", "TOKE", "PASS"] {
+            assert!(!patterns.is_secret(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn configured_and_environment_patterns_add_to_the_defaults() {
+        env::set_var(SECRET_PATTERNS_VAR, "cookie, ,session");
+        let patterns = SecretPatterns::from_env(&["pin".to_string()]);
+        env::remove_var(SECRET_PATTERNS_VAR);
+
+        for key in ["CARD_PIN", "AUTH_COOKIE", "session_id", "AWS_SECRET_ACCESS_KEY"] {
+            assert!(patterns.is_secret(key), "{}", key);
+        }
+        // The blank entry between the commas does not match everything
+        assert!(!patterns.is_secret("LANG"));
+    }
+
+    #[test]
+    fn blank_patterns_are_dropped() {
+        let patterns = SecretPatterns::new(["".to_string(), "  ".to_string()]);
+        assert!(!patterns.is_secret("ANYTHING"));
+    }
+
+    #[test]
+    fn splits_and_sorts_entries() {
+        let environ = ["TERM=xterm", "", "EMPTY=", "NO_EQUALS", "OPTS=a=b"].map(str::to_string);
+        let vars = environment(&environ, &SecretPatterns::new([]), "");
+        let pairs: Vec<(&str, &str)> = vars.iter().map(|var| (var.key.as_str(), var.value.as_str())).collect();
+        assert_eq!(pairs, [("EMPTY", ""), ("NO_EQUALS", ""), ("OPTS", "a=b"), ("TERM", "xterm")]);
+    }
+
+    #[test]
+    fn masks_secret_values_until_revealed() {
+        let environ = ["API_TOKEN=hunter2".to_string()];
+        let vars = environment(&environ, &SecretPatterns::from_env(&[]), "");
+        assert!(vars[0].secret);
+        assert_eq!(vars[0].display_value(false), MASK);
+        assert_eq!(vars[0].display_value(true), "hunter2");
+    }
+
+    #[test]
+    fn queries_never_match_masked_values() {
+        let environ = ["API_TOKEN=hunter2", "GREETING=Hunter2", "USER=alice"].map(str::to_string);
+        let patterns = SecretPatterns::from_env(&[]);
+        assert_eq!(keys(&environment(&environ, &patterns, "hunter")), ["GREETING"]);
+        assert_eq!(keys(&environment(&environ, &patterns, "HUNTER2")), ["GREETING"]);
+        // Names are still searchable
+        assert_eq!(keys(&environment(&environ, &patterns, "token")), ["API_TOKEN"]);
+        assert_eq!(keys(&environment(&environ, &patterns, "")), ["API_TOKEN", "GREETING", "USER"]);
+    }
+}
@@ -15,15 +15,58 @@
  **********************************************************************
  */
 
-use std::cmp::Ordering;
-use sysinfo::{Pid, System, Process as SysProcess, ProcessRefreshKind, RefreshKind};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+use sysinfo::{Pid, Uid};
+use serde::{Deserialize, Serialize};
+use crate::{ids, procfs::MemoryBreakdown};
 
-#[derive(Debug, Clone)]
+mod column;
+mod detail;
+mod environ;
+
+pub use column::{format_row, Column, MemoryKind};
+pub use detail::{format_duration, format_timestamp, ProcessDetail};
+pub use environ::{environment, SecretPatterns};
+
+// Filters that ProcessList::select_pid had to clear to reach a process
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Cleared {
+    pub filter: bool,
+    pub kernel_threads: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProcessKind {
+    Process,
+    KernelThread,
+    Thread,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Process {
+    #[serde(deserialize_with = "ids::id")]
     pub pid: Pid,
+    #[serde(deserialize_with = "ids::optional_id")]
+    pub parent: Option<Pid>,
+    #[serde(deserialize_with = "ids::optional_id")]
+    pub uid: Option<Uid>,
+    pub user: Option<String>,
     pub name: String,
     pub cpu_usage: f32,
     pub memory_usage: u64,
+    pub memory: Option<MemoryBreakdown>,
+    pub state: char,
+    pub last_cpu: Option<u32>,
+    pub kind: ProcessKind,
+    // When the process started, in whatever clock its source keeps; only compared to tell a
+    // reused pid from the process that had it before
+    #[serde(skip)]
+    pub start_time: u64,
+    // Indentation level in tree mode; threads sit one level below their process
+    pub depth: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -31,108 +74,306 @@ pub enum SortField {
     Pid,
     Name,
     Cpu,
-    Memory,
+    Memory(MemoryKind),
+}
+
+impl SortField {
+    // Names and pids read naturally ascending, resource usage descending
+    pub fn default_order(self) -> SortOrder {
+        match self {
+            SortField::Pid | SortField::Name => SortOrder::Ascending,
+            SortField::Cpu | SortField::Memory(_) => SortOrder::Descending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
 }
 
 pub struct ProcessList {
     processes: Vec<Process>,
+    entries: Vec<Process>,
+    threads: HashMap<Pid, Vec<Process>>,
     selected_index: usize,
+    // The selection follows its process when the rows are rebuilt
+    selected_pid: Option<Pid>,
     sort_field: SortField,
+    sort_order: SortOrder,
     filter: Option<String>,
-    system: System,
+    restrict_pids: Vec<Pid>,
+    restrict_users: Vec<String>,
+    tree: bool,
+    show_threads: bool,
+    hide_kernel_threads: bool,
+}
+
+impl Default for ProcessList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ProcessList {
     pub fn new() -> Self {
         Self {
             processes: Vec::new(),
+            entries: Vec::new(),
+            threads: HashMap::new(),
             selected_index: 0,
+            selected_pid: None,
             sort_field: SortField::Cpu,
+            sort_order: SortOrder::Descending,
             filter: None,
-            system: System::new_with_specifics(
-                RefreshKind::new()
-                    .with_processes(ProcessRefreshKind::everything())
-            ),
+            restrict_pids: Vec::new(),
+            restrict_users: Vec::new(),
+            tree: false,
+            show_threads: false,
+            hide_kernel_threads: false,
+        }
+    }
+
+    // Takes the processes of a new snapshot; returns whether the listed rows changed. Entries are
+    // updated in place and only re-sorted when processes started or one of their sort keys changed
+    pub fn apply(&mut self, processes: &[Process], threads: &HashMap<Pid, Vec<Process>>) -> bool {
+        let field = self.sort_field;
+        let mut latest: HashMap<Pid, &Process> = processes.iter().map(|process| (process.pid, process)).collect();
+        let mut changed = false;
+        let mut resort = false;
+        self.entries.retain_mut(|entry| match latest.remove(&entry.pid) {
+            Some(process) => {
+                if process != entry {
+                    resort |= compare(entry, process, field, SortOrder::Ascending) != Ordering::Equal;
+                    changed = true;
+                    entry.clone_from(process);
+                }
+                true
+            }
+            None => {
+                changed = true;
+                false
+            }
+        });
+        if !latest.is_empty() {
+            self.entries.extend(processes.iter().filter(|process| latest.contains_key(&process.pid)).cloned());
+            changed = true;
+            resort = true;
+        }
+
+        if self.show_threads && self.threads != *threads {
+            self.threads = threads.clone();
+            changed = true;
+            resort = true;
         }
+
+        if !changed {
+            return false;
+        }
+        if resort {
+            self.sort();
+        }
+        let previous = std::mem::take(&mut self.processes);
+        self.build_rows();
+        self.processes != previous
     }
 
-    pub fn update(&mut self) {
-        self.system.refresh_processes();
-        
-        self.processes = self.system.processes()
+    fn rebuild(&mut self) {
+        self.sort();
+        self.build_rows();
+    }
+
+    fn sort(&mut self) {
+        let (field, order) = (self.sort_field, self.sort_order);
+        self.entries.sort_by(|a, b| compare(a, b, field, order));
+        for threads in self.threads.values_mut() {
+            threads.sort_by(|a, b| compare(a, b, field, order));
+        }
+    }
+
+    // Filters the sorted top-level entries, then expands threads beneath their process
+    fn build_rows(&mut self) {
+        let filter = self.filter.as_ref().map(|f| f.to_lowercase());
+        let visible: Vec<&Process> = self
+            .entries
             .iter()
-            .map(|(pid, process)| Process {
-                pid: *pid,
-                name: process.name().to_string(),
-                cpu_usage: process.cpu_usage(),
-                memory_usage: process.memory(),
-            })
+            .filter(|process| self.is_visible(process, filter.as_deref()))
             .collect();
 
-        self.sort_processes();
-        self.apply_filter();
-        self.clamp_selection();
+        let threads = if self.show_threads { Some(&self.threads) } else { None };
+        let mut rows = Vec::with_capacity(visible.len());
+        if self.tree {
+            for (process, depth) in tree_order(&visible) {
+                push_row(&mut rows, process, depth, threads);
+            }
+        } else {
+            for process in visible {
+                push_row(&mut rows, process, 0, threads);
+            }
+        }
+        self.processes = rows;
+
+        match self.selected_pid.and_then(|pid| self.processes.iter().position(|p| p.pid == pid)) {
+            Some(index) => self.selected_index = index,
+            None => self.select(self.selected_index),
+        }
     }
 
-    fn sort_processes(&mut self) {
-        self.processes.sort_by(|a, b| {
-            match self.sort_field {
-                SortField::Pid => a.pid.cmp(&b.pid),
-                SortField::Name => a.name.cmp(&b.name),
-                SortField::Cpu => b.cpu_usage.partial_cmp(&a.cpu_usage).unwrap_or(Ordering::Equal),
-                SortField::Memory => b.memory_usage.cmp(&a.memory_usage),
-            }
-        });
+    fn is_visible(&self, process: &Process, filter: Option<&str>) -> bool {
+        if self.hide_kernel_threads && process.kind == ProcessKind::KernelThread {
+            return false;
+        }
+        self.is_allowed(process) && filter.is_none_or(|filter| process.name.to_lowercase().contains(filter))
     }
 
-    fn apply_filter(&mut self) {
-        if let Some(filter) = &self.filter {
-            self.processes.retain(|process| {
-                process.name.to_lowercase().contains(&filter.to_lowercase())
+    // Whether the -p and -u restrictions let the process be listed
+    fn is_allowed(&self, process: &Process) -> bool {
+        if !self.restrict_pids.is_empty() && !self.restrict_pids.contains(&process.pid) {
+            return false;
+        }
+        if !self.restrict_users.is_empty() {
+            let matches = self.restrict_users.iter().any(|user| {
+                process.user.as_deref() == Some(user.as_str())
+                    || (process.uid.is_some() && user.parse::<Uid>().ok() == process.uid)
             });
+            if !matches {
+                return false;
+            }
         }
+        true
     }
 
     pub fn filter(&mut self, query: &str) {
         self.filter = Some(query.to_string());
-        self.apply_filter();
-        self.clamp_selection();
+        self.rebuild();
     }
 
     pub fn set_sort_field(&mut self, field: SortField) {
         self.sort_field = field;
-        self.sort_processes();
+        self.sort_order = field.default_order();
+        self.rebuild();
+    }
+
+    pub fn set_sort_order(&mut self, order: SortOrder) {
+        self.sort_order = order;
+        self.rebuild();
+    }
+
+    pub fn invert_sort_order(&mut self) {
+        self.set_sort_order(match self.sort_order {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        });
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort_order
+    }
+
+    // Only the given pids and users (names or numeric ids) are listed; empty means no restriction
+    pub fn restrict(&mut self, pids: Vec<Pid>, users: Vec<String>) {
+        self.restrict_pids = pids;
+        self.restrict_users = users;
+        self.rebuild();
+    }
+
+    pub fn set_tree(&mut self, tree: bool) {
+        self.tree = tree;
+        self.rebuild();
+    }
+
+    pub fn tree(&self) -> bool {
+        self.tree
+    }
+
+    // Selecting memory sort again cycles through RSS, PSS, USS, shared and swap
+    pub fn cycle_memory_sort(&mut self) {
+        let kind = match self.sort_field {
+            SortField::Memory(kind) => kind.next(),
+            _ => MemoryKind::Rss,
+        };
+        self.set_sort_field(SortField::Memory(kind));
+    }
+
+    pub fn sort_field(&self) -> SortField {
+        self.sort_field
+    }
+
+    // Threads are only collected while shown, so they appear with the next snapshot
+    pub fn toggle_threads(&mut self) {
+        self.show_threads = !self.show_threads;
+        if !self.show_threads {
+            self.threads.clear();
+        }
+        self.rebuild();
+    }
+
+    pub fn toggle_kernel_threads(&mut self) {
+        self.hide_kernel_threads = !self.hide_kernel_threads;
+        self.rebuild();
+    }
+
+    pub fn show_threads(&self) -> bool {
+        self.show_threads
+    }
+
+    pub fn hide_kernel_threads(&self) -> bool {
+        self.hide_kernel_threads
     }
 
     pub fn move_selection(&mut self, delta: i32) {
-        let new_index = self.selected_index as i32 + delta;
-        self.selected_index = new_index.clamp(0, self.processes.len().saturating_sub(1) as i32) as usize;
+        self.select((self.selected_index as i32 + delta).max(0) as usize);
+    }
+
+    pub fn select_index(&mut self, index: usize) {
+        if index < self.processes.len() {
+            self.select(index);
+        }
     }
 
     pub fn move_to_start(&mut self) {
-        self.selected_index = 0;
+        self.select(0);
     }
 
     pub fn move_to_end(&mut self) {
-        self.selected_index = self.processes.len().saturating_sub(1);
+        self.select(self.processes.len().saturating_sub(1));
     }
 
-    fn clamp_selection(&mut self) {
-        if !self.processes.is_empty() {
-            self.selected_index = self.selected_index.min(self.processes.len() - 1);
-        } else {
-            self.selected_index = 0;
-        }
+    // Selects the row at `index`, or the last one beyond the end
+    fn select(&mut self, index: usize) {
+        self.selected_index = index.min(self.processes.len().saturating_sub(1));
+        self.selected_pid = self.processes.get(self.selected_index).map(|p| p.pid);
     }
 
     pub fn selected_pid(&self) -> Option<Pid> {
         self.processes.get(self.selected_index).map(|p| p.pid)
     }
 
-    pub fn kill_process(&mut self, pid: Pid) {
-        if let Some(process) = self.system.process(pid) {
-            process.kill();
+    // Moves the selection to `pid`, clearing filters that would hide it; None, with nothing cleared,
+    // when it is not listed even then
+    pub fn select_pid(&mut self, pid: Pid) -> Option<Cleared> {
+        let mut cleared = Cleared::default();
+        if !self.processes.iter().any(|p| p.pid == pid) {
+            if !self.entries.iter().any(|p| p.pid == pid && self.is_allowed(p)) {
+                return None;
+            }
+            cleared.filter = self.filter.take().is_some_and(|filter| !filter.is_empty());
+            cleared.kernel_threads = std::mem::replace(&mut self.hide_kernel_threads, false);
+            self.rebuild();
         }
+        let index = self.processes.iter().position(|p| p.pid == pid)?;
+        self.select(index);
+        Some(cleared)
+    }
+
+    // Whether -p or -u limit the listed processes
+    pub fn restricted(&self) -> bool {
+        !self.restrict_pids.is_empty() || !self.restrict_users.is_empty()
+    }
+
+    pub fn name_of(&self, pid: Pid) -> Option<&str> {
+        self.entries.iter().find(|process| process.pid == pid).map(|process| process.name.as_str())
     }
 
     pub fn processes(&self) -> &[Process] {
@@ -142,4 +383,140 @@ impl ProcessList {
     pub fn selected_index(&self) -> usize {
         self.selected_index
     }
-} 
\ No newline at end of file
+}
+
+fn push_row(rows: &mut Vec<Process>, process: &Process, depth: usize, threads: Option<&HashMap<Pid, Vec<Process>>>) {
+    rows.push(Process { depth, ..process.clone() });
+    if let Some(threads) = threads.and_then(|threads| threads.get(&process.pid)) {
+        rows.extend(threads.iter().map(|thread| Process { depth: depth + 1, ..thread.clone() }));
+    }
+}
+
+// Depth-first order of already sorted processes, so siblings keep the sort order
+fn tree_order<'a>(processes: &[&'a Process]) -> Vec<(&'a Process, usize)> {
+    let pids: HashSet<Pid> = processes.iter().map(|process| process.pid).collect();
+    let mut children: HashMap<Pid, Vec<&'a Process>> = HashMap::new();
+    let mut roots = Vec::new();
+    for process in processes {
+        match process.parent.filter(|parent| pids.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(process),
+            None => roots.push(*process),
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(processes.len());
+    let mut seen = HashSet::new();
+    let mut stack: Vec<(&Process, usize)> = roots.into_iter().rev().map(|process| (process, 0)).collect();
+    // Processes caught in a parent cycle (pid reuse) have no root; list them at the top level
+    let mut orphans = processes.iter();
+    while let Some((process, depth)) = stack.pop().or_else(|| {
+        orphans.find(|process| !seen.contains(&process.pid)).map(|process| (*process, 0))
+    }) {
+        if !seen.insert(process.pid) {
+            continue;
+        }
+        ordered.push((process, depth));
+        if let Some(kids) = children.get(&process.pid) {
+            stack.extend(kids.iter().rev().map(|kid| (*kid, depth + 1)));
+        }
+    }
+    ordered
+}
+
+fn compare(a: &Process, b: &Process, field: SortField, order: SortOrder) -> Ordering {
+    let ordering = match field {
+        SortField::Pid => a.pid.cmp(&b.pid),
+        SortField::Name => a.name.cmp(&b.name),
+        SortField::Cpu => a.cpu_usage.partial_cmp(&b.cpu_usage).unwrap_or(Ordering::Equal),
+        SortField::Memory(kind) => kind.value(a).cmp(&kind.value(b)),
+    };
+    match order {
+        SortOrder::Ascending => ordering,
+        SortOrder::Descending => ordering.reverse(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, cpu_usage: f32, kind: ProcessKind) -> Process {
+        Process {
+            pid: Pid::from_u32(pid),
+            parent: None,
+            uid: None,
+            user: Some("root".to_string()),
+            name: name.to_string(),
+            cpu_usage,
+            memory_usage: 0,
+            memory: None,
+            state: 'S',
+            last_cpu: None,
+            kind,
+            start_time: 0,
+            depth: 0,
+        }
+    }
+
+    fn pids(list: &ProcessList) -> Vec<u32> {
+        list.processes().iter().map(|process| process.pid.as_u32()).collect()
+    }
+
+    #[test]
+    fn selection_follows_its_process() {
+        let mut list = ProcessList::new();
+        let threads = HashMap::new();
+        let init = process(1, "init", 30.0, ProcessKind::Process);
+        list.apply(&[init.clone(), process(2, "sshd", 10.0, ProcessKind::Process)], &threads);
+        list.move_selection(1);
+        assert_eq!(list.selected_pid(), Some(Pid::from_u32(2)));
+
+        let idle = Process { cpu_usage: 0.0, ..init };
+        list.apply(&[idle.clone(), process(2, "sshd", 10.0, ProcessKind::Process)], &threads);
+        assert_eq!(pids(&list), [2, 1]);
+        assert_eq!((list.selected_index(), list.selected_pid()), (0, Some(Pid::from_u32(2))));
+
+        // Once it exits, the row at the same place is selected
+        list.apply(&[idle], &threads);
+        assert_eq!(list.selected_pid(), Some(Pid::from_u32(1)));
+    }
+
+    #[test]
+    fn select_pid_clears_what_hides_it() {
+        let mut list = ProcessList::new();
+        list.toggle_kernel_threads();
+        list.filter("ssh");
+        list.apply(
+            &[
+                process(1, "init", 0.0, ProcessKind::Process),
+                process(2, "kworker", 0.0, ProcessKind::KernelThread),
+                process(3, "sshd", 0.0, ProcessKind::Process),
+            ],
+            &HashMap::new(),
+        );
+        assert_eq!(list.select_pid(Pid::from_u32(3)), Some(Cleared::default()));
+
+        let cleared = list.select_pid(Pid::from_u32(2)).unwrap();
+        assert_eq!(cleared, Cleared { filter: true, kernel_threads: true });
+        assert_eq!(list.selected_pid(), Some(Pid::from_u32(2)));
+        assert!(!list.hide_kernel_threads());
+    }
+
+    #[test]
+    fn select_pid_keeps_restrictions() {
+        let mut list = ProcessList::new();
+        list.restrict(vec![Pid::from_u32(1)], Vec::new());
+        list.filter("in");
+        list.apply(
+            &[process(1, "init", 0.0, ProcessKind::Process), process(3, "sshd", 0.0, ProcessKind::Process)],
+            &HashMap::new(),
+        );
+        assert!(list.restricted());
+        assert_eq!(list.select_pid(Pid::from_u32(3)), None);
+        assert_eq!(list.select_pid(Pid::from_u32(4)), None);
+        // Nothing was cleared on the way
+        assert_eq!(pids(&list), [1]);
+        list.filter("");
+        assert_eq!(pids(&list), [1]);
+    }
+}
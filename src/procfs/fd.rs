@@ -0,0 +1,194 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : procfs/fd.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Open file descriptors from /proc/<pid>/fd and fdinfo
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{collections::HashMap, fmt, fs, io, path::Path};
+use serde::{Deserialize, Serialize};
+use super::net;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FdKind {
+    File,
+    Directory,
+    Device,
+    Pipe,
+    Socket,
+    AnonInode,
+    Other,
+}
+
+impl fmt::Display for FdKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FdKind::File => "file",
+            FdKind::Directory => "dir",
+            FdKind::Device => "device",
+            FdKind::Pipe => "pipe",
+            FdKind::Socket => "socket",
+            FdKind::AnonInode => "anon_inode",
+            FdKind::Other => "other",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDescriptor {
+    pub fd: u32,
+    pub kind: FdKind,
+    pub target: String,
+    pub flags: Option<u32>,
+    pub pos: Option<u64>,
+}
+
+// Fails only when the fd directory itself cannot be listed (usually EACCES)
+pub fn read_fds(dir: &Path) -> io::Result<Vec<FileDescriptor>> {
+    let entries = fs::read_dir(dir.join("fd"))?;
+    let sockets = net::sockets_by_inode(&dir.join("net"));
+
+    let mut fds: Vec<FileDescriptor> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let fd: u32 = entry.file_name().to_str()?.parse().ok()?;
+            // The descriptor may have been closed since the directory was listed
+            let path = entry.path();
+            let link = fs::read_link(&path).ok()?;
+            let link = link.to_string_lossy();
+
+            let (kind, target) = classify(&link, &path, &sockets);
+            let (flags, pos) = read_fdinfo(dir, fd);
+
+            Some(FileDescriptor { fd, kind, target, flags, pos })
+        })
+        .collect();
+
+    fds.sort_by_key(|fd| fd.fd);
+    Ok(fds)
+}
+
+// `path` is the fd entry itself; stat follows it to the open file as the kernel resolves it for the
+// process, which the link text does not in another mount namespace or once the file is deleted
+fn classify(link: &str, path: &Path, sockets: &HashMap<u64, net::SocketEntry>) -> (FdKind, String) {
+    if let Some(inode) = link.strip_prefix("socket:[").and_then(|rest| rest.strip_suffix(']')) {
+        let target = inode
+            .parse()
+            .ok()
+            .and_then(|inode: u64| sockets.get(&inode))
+            .map_or_else(|| link.to_string(), |socket| socket.to_string());
+        return (FdKind::Socket, target);
+    }
+
+    let kind = if link.starts_with("pipe:[") {
+        FdKind::Pipe
+    } else if link.starts_with("anon_inode:") {
+        FdKind::AnonInode
+    } else if link.starts_with("/dev/") {
+        FdKind::Device
+    } else if link.starts_with('/') {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => FdKind::Directory,
+            _ => FdKind::File,
+        }
+    } else {
+        FdKind::Other
+    };
+    (kind, link.to_string())
+}
+
+fn read_fdinfo(dir: &Path, fd: u32) -> (Option<u32>, Option<u64>) {
+    let Ok(content) = fs::read_to_string(dir.join("fdinfo").join(fd.to_string())) else {
+        return (None, None);
+    };
+
+    let mut flags = None;
+    let mut pos = None;
+    for line in content.lines() {
+        if let Some(value) = line.strip_prefix("flags:") {
+            flags = u32::from_str_radix(value.trim(), 8).ok();
+        } else if let Some(value) = line.strip_prefix("pos:") {
+            pos = value.trim().parse().ok();
+        }
+    }
+    (flags, pos)
+}
+
+// open(2) flags by name; their values differ between architectures
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const FLAG_NAMES: [(i32, &str); 8] = [
+    (libc::O_APPEND, "append"),
+    (libc::O_NONBLOCK, "nonblock"),
+    (libc::O_DSYNC, "dsync"),
+    (libc::O_DIRECT, "direct"),
+    (libc::O_DIRECTORY, "directory"),
+    (libc::O_NOFOLLOW, "nofollow"),
+    (libc::O_CLOEXEC, "cloexec"),
+    (libc::O_SYNC, "sync"),
+];
+
+// fdinfo only exists on Linux
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const FLAG_NAMES: [(i32, &str); 0] = [];
+
+// Renders open(2) flags as shown in fdinfo (octal) into short readable names
+pub fn describe_flags(flags: u32) -> String {
+    let flags = flags as i32;
+    let set = |bit: i32| bit != 0 && flags & bit == bit;
+    // O_SYNC includes the O_DSYNC bit
+    let sync = FLAG_NAMES.iter().any(|(bit, name)| *name == "sync" && set(*bit));
+
+    let mut parts = vec![match flags & 0o3 {
+        0 => "r",
+        1 => "w",
+        _ => "rw",
+    }];
+    parts.extend(
+        FLAG_NAMES
+            .iter()
+            .filter(|(bit, name)| set(*bit) && !(sync && *name == "dsync"))
+            .map(|(_, name)| *name),
+    );
+    parts.join(",")
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn describe(flags: i32) -> String {
+        describe_flags(flags as u32)
+    }
+
+    #[test]
+    fn describes_flags() {
+        assert_eq!(describe(libc::O_RDONLY), "r");
+        assert_eq!(describe(libc::O_WRONLY | libc::O_APPEND | libc::O_CLOEXEC), "w,append,cloexec");
+        assert_eq!(describe(libc::O_RDWR | libc::O_NONBLOCK), "rw,nonblock");
+        assert_eq!(describe(libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC), "r,directory,cloexec");
+        assert_eq!(describe(libc::O_WRONLY | libc::O_DIRECT), "w,direct");
+        assert_eq!(describe(libc::O_WRONLY | libc::O_DSYNC), "w,dsync");
+        assert_eq!(describe(libc::O_WRONLY | libc::O_SYNC), "w,sync");
+        // Set by the kernel on every open of a 64-bit process where it is not 0
+        assert_eq!(describe(libc::O_RDONLY | libc::O_LARGEFILE), "r");
+    }
+
+    // As read from fdinfo on x86-64
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn describes_x86_64_fdinfo() {
+        assert_eq!(describe_flags(0o2100002), "rw,cloexec");
+        assert_eq!(describe_flags(0o2304000), "r,nonblock,directory,cloexec");
+        assert_eq!(describe_flags(0o4010001), "w,sync");
+    }
+}
@@ -0,0 +1,147 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : procfs/mod.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Direct /proc readers for data sysinfo does not expose
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use serde::{Deserialize, Serialize};
+
+mod fd;
+pub mod net;
+mod smaps;
+
+pub use fd::{describe_flags, read_fds, FdKind, FileDescriptor};
+pub use smaps::{
+    group_by_path, read_smaps, read_smaps_rollup, sort_mappings, totals, MapSort, Mapping, MemoryBreakdown,
+};
+
+// Where the proc filesystem of the machine we run on is mounted; a source may read another
+// one, e.g. /host/proc inside a container
+pub const PROC_ROOT: &str = "/proc";
+
+#[derive(Debug, Clone, Default)]
+pub struct Stat {
+    pub name: String,
+    pub state: char,
+    pub parent: u32,
+    pub flags: u32,
+    // User plus system time, in clock ticks
+    pub cpu_time: u64,
+    pub priority: i64,
+    pub nice: i64,
+    // Clock ticks after boot
+    pub start_time: u64,
+    // Resident pages
+    pub rss: u64,
+    pub processor: u32,
+}
+
+// Real, effective and saved ids from /proc/<pid>/status
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Credentials {
+    pub uid: [u32; 3],
+    pub gid: [u32; 3],
+}
+
+// The directory of a process below `root`; works for thread ids as well, /proc/<tid> is
+// reachable even though it is not listed. The readers below take such a directory
+pub fn pid_path(root: &Path, pid: u32) -> PathBuf {
+    root.join(pid.to_string())
+}
+
+// `dir` is /proc/<pid> or /proc/<pid>/task/<tid>
+pub fn read_stat(dir: &Path) -> Option<Stat> {
+    let content = fs::read_to_string(dir.join("stat")).ok()?;
+    parse_stat(&content)
+}
+
+fn parse_stat(content: &str) -> Option<Stat> {
+    // The command name is wrapped in parentheses and may itself contain spaces or ')'
+    let open = content.find('(')?;
+    let close = content.rfind(')')?;
+    let fields: Vec<&str> = content[close + 1..].split_whitespace().collect();
+
+    // Field indexes below are offset by 3 from proc(5) numbering (pid, comm are consumed)
+    let field = |n: usize| fields.get(n - 3).copied().unwrap_or("0");
+    let number = |n: usize| field(n).parse::<u64>().unwrap_or(0);
+
+    Some(Stat {
+        name: content.get(open + 1..close).unwrap_or_default().to_string(),
+        state: field(3).chars().next().unwrap_or('?'),
+        parent: field(4).parse().unwrap_or(0),
+        flags: field(9).parse().unwrap_or(0),
+        cpu_time: number(14) + number(15),
+        priority: field(18).parse().unwrap_or(0),
+        nice: field(19).parse().unwrap_or(0),
+        start_time: number(22),
+        rss: number(24),
+        processor: field(39).parse().unwrap_or(0),
+    })
+}
+
+pub fn read_credentials(dir: &Path) -> Option<Credentials> {
+    let content = fs::read_to_string(dir.join("status")).ok()?;
+    let mut credentials = Credentials::default();
+
+    for line in content.lines() {
+        let target = if let Some(rest) = line.strip_prefix("Uid:") {
+            Some((&mut credentials.uid, rest))
+        } else {
+            line.strip_prefix("Gid:").map(|rest| (&mut credentials.gid, rest))
+        };
+        if let Some((ids, rest)) = target {
+            for (slot, value) in ids.iter_mut().zip(rest.split_whitespace()) {
+                *slot = value.parse().unwrap_or(0);
+            }
+        }
+    }
+
+    Some(credentials)
+}
+
+pub fn read_cgroups(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join("cgroup"))
+        .map(|content| content.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+// Each entry of /proc/<pid>/ns is a symlink such as "net:[4026531840]"
+pub fn read_namespaces(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(dir.join("ns")) else {
+        return Vec::new();
+    };
+
+    let mut namespaces: Vec<(String, String)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let target = fs::read_link(entry.path()).ok()?;
+            Some((
+                entry.file_name().to_string_lossy().into_owned(),
+                target.to_string_lossy().into_owned(),
+            ))
+        })
+        .collect();
+    namespaces.sort();
+    namespaces
+}
+
+pub fn read_limits(dir: &Path) -> Vec<String> {
+    fs::read_to_string(dir.join("limits"))
+        .map(|content| content.lines().map(|line| line.trim_end().to_string()).collect())
+        .unwrap_or_default()
+}
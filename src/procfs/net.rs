@@ -0,0 +1,363 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : procfs/net.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Socket tables from /proc/net/{tcp,tcp6,udp,udp6,unix}
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt, fs,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+use serde::{Deserialize, Serialize};
+use super::PROC_ROOT;
+
+// Set on unix sockets that are accepting connections (__SO_ACCEPTCON)
+const UNIX_ACCEPTING: u32 = 0x0001_0000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Protocol {
+    Tcp,
+    Tcp6,
+    Udp,
+    Udp6,
+    Unix,
+}
+
+impl Protocol {
+    const ALL: [Protocol; 5] = [Protocol::Tcp, Protocol::Tcp6, Protocol::Udp, Protocol::Udp6, Protocol::Unix];
+    const INET: [Protocol; 4] = [Protocol::Tcp, Protocol::Tcp6, Protocol::Udp, Protocol::Udp6];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Tcp6 => "tcp6",
+            Protocol::Udp => "udp",
+            Protocol::Udp6 => "udp6",
+            Protocol::Unix => "unix",
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.file_name())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocketEntry {
+    pub protocol: Protocol,
+    pub local: String,
+    pub remote: String,
+    // Owned only when read back from serialized data
+    pub state: Cow<'static, str>,
+    pub inode: u64,
+}
+
+impl fmt::Display for SocketEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.protocol {
+            Protocol::Unix if self.local.is_empty() => write!(f, "unix {}", self.state),
+            Protocol::Unix => write!(f, "unix {} {}", self.local, self.state),
+            _ if self.state == "LISTEN" || self.state == "UNCONN" => {
+                write!(f, "{} {} ({})", self.protocol, self.local, self.state)
+            }
+            _ => write!(f, "{} {} -> {} ({})", self.protocol, self.local, self.remote, self.state),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Connection {
+    pub socket: SocketEntry,
+    pub pid: Option<u32>,
+}
+
+// TCP and UDP sockets of the host network namespace with the process owning each one.
+// /proc/net links to the namespace of whoever reads it, so below another root the tables
+// of init are read instead
+pub fn read_connections(root: &Path) -> Vec<Connection> {
+    let owners = socket_owners(root);
+    let net_dir = if root == Path::new(PROC_ROOT) { root.join("net") } else { root.join("1/net") };
+    let mut connections: Vec<Connection> = read_tables(&net_dir, &Protocol::INET)
+        .into_iter()
+        .map(|socket| Connection { pid: owners.get(&socket.inode).copied(), socket })
+        .collect();
+
+    // Listening sockets first, then by protocol and local address
+    connections.sort_by(|a, b| {
+        (a.socket.state != "LISTEN" && a.socket.state != "UNCONN")
+            .cmp(&(b.socket.state != "LISTEN" && b.socket.state != "UNCONN"))
+            .then_with(|| (a.socket.protocol as u8).cmp(&(b.socket.protocol as u8)))
+            .then_with(|| a.socket.local.cmp(&b.socket.local))
+    });
+    connections
+}
+
+// Maps socket inodes to the pid holding them by scanning /proc/<pid>/fd; unreadable processes are skipped
+fn socket_owners(root: &Path) -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = fs::read_dir(root) else {
+        return owners;
+    };
+
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(link) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            let link = link.to_string_lossy();
+            if let Some(inode) = link
+                .strip_prefix("socket:[")
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|inode| inode.parse().ok())
+            {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+// Reads every socket table below `net_dir`, which is either /proc/net or /proc/<pid>/net
+pub fn read_sockets(net_dir: &Path) -> Vec<SocketEntry> {
+    read_tables(net_dir, &Protocol::ALL)
+}
+
+fn read_tables(net_dir: &Path, protocols: &[Protocol]) -> Vec<SocketEntry> {
+    protocols
+        .iter()
+        .filter_map(|&protocol| {
+            let content = fs::read_to_string(net_dir.join(protocol.file_name())).ok()?;
+            Some(parse_table(protocol, &content))
+        })
+        .flatten()
+        .collect()
+}
+
+pub fn sockets_by_inode(net_dir: &Path) -> HashMap<u64, SocketEntry> {
+    read_sockets(net_dir)
+        .into_iter()
+        .filter(|socket| socket.inode != 0)
+        .map(|socket| (socket.inode, socket))
+        .collect()
+}
+
+fn parse_table(protocol: Protocol, content: &str) -> Vec<SocketEntry> {
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match protocol {
+                Protocol::Unix => parse_unix(&fields),
+                _ => parse_inet(protocol, &fields),
+            }
+        })
+        .collect()
+}
+
+// sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+fn parse_inet(protocol: Protocol, fields: &[&str]) -> Option<SocketEntry> {
+    let local = parse_address(fields.get(1)?)?;
+    let remote = parse_address(fields.get(2)?)?;
+    let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+    let inode = fields.get(9)?.parse().ok()?;
+
+    let state = match protocol {
+        Protocol::Udp | Protocol::Udp6 => match state {
+            0x01 => "ESTAB",
+            _ => "UNCONN",
+        },
+        _ => tcp_state(state),
+    };
+
+    Some(SocketEntry { protocol, local, remote, state: state.into(), inode })
+}
+
+// Num RefCount Protocol Flags Type St Inode Path
+fn parse_unix(fields: &[&str]) -> Option<SocketEntry> {
+    let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+    let state = u8::from_str_radix(fields.get(5)?, 16).ok()?;
+    let inode = fields.get(6)?.parse().ok()?;
+    let path = fields.get(7).map(|p| p.to_string()).unwrap_or_default();
+
+    let state = if flags & UNIX_ACCEPTING != 0 {
+        "LISTEN"
+    } else {
+        match state {
+            0x01 => "UNCONN",
+            0x02 => "CONNECTING",
+            0x03 => "CONNECTED",
+            0x04 => "DISCONNECTING",
+            _ => "UNKNOWN",
+        }
+    };
+
+    Some(SocketEntry {
+        protocol: Protocol::Unix,
+        local: path,
+        remote: String::new(),
+        state: state.into(),
+        inode,
+    })
+}
+
+fn tcp_state(state: u8) -> &'static str {
+    match state {
+        0x01 => "ESTABLISHED",
+        0x02 => "SYN_SENT",
+        0x03 => "SYN_RECV",
+        0x04 => "FIN_WAIT1",
+        0x05 => "FIN_WAIT2",
+        0x06 => "TIME_WAIT",
+        0x07 => "CLOSE",
+        0x08 => "CLOSE_WAIT",
+        0x09 => "LAST_ACK",
+        0x0A => "LISTEN",
+        0x0B => "CLOSING",
+        _ => "UNKNOWN",
+    }
+}
+
+// Addresses are hex words in host byte order, e.g. "0100007F:1F90" is 127.0.0.1:8080
+fn parse_address(field: &str) -> Option<String> {
+    let (address, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    match address.len() {
+        8 => {
+            let word = u32::from_str_radix(address, 16).ok()?;
+            Some(format!("{}:{}", Ipv4Addr::from(word.to_ne_bytes()), port))
+        }
+        32 => {
+            let mut octets = [0u8; 16];
+            for (i, chunk) in octets.chunks_mut(4).enumerate() {
+                let word = u32::from_str_radix(&address[i * 8..i * 8 + 8], 16).ok()?;
+                chunk.copy_from_slice(&word.to_ne_bytes());
+            }
+            Some(format!("[{}]:{}", Ipv6Addr::from(octets), port))
+        }
+        _ => None,
+    }
+}
+
+// The kernel writes addresses as words in host byte order; these tables come from a little-endian machine
+#[cfg(all(test, target_endian = "little"))]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:07E8 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 662 1 0000000091a5b41d 100 0 0 10 0
+   1: 0100007F:1F91 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 206994 1 00000000e71c1012 100 0 0 10 0
+   3: 0100007F:E01A 0100007F:1F91 01 00000000:00000000 00:00000000 00000000     0        0 206995 2 00000000438e4a79 20 0 0 10 -1
+   4: 0F02000A:0016 0202000A:C350 06 00000000:00000000 03:00000E7B 00000000     0        0 0 3 0000000000000000
+";
+
+    const TCP6: &str = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 206991 1 00000000492c7b8c 100 0 0 10 0
+   1: 00000000000000000000000001000000:1F90 00000000000000000000000001000000:CEDE 01 00000000:00000000 00:00000000 00000000     0        0 206993 1 000000007e18d407 20 0 0 10 -1
+   2: B80D0120000000000000000001000000:01BB 0000000000000000FFFF00000F02000A:D431 08 00000000:00000000 00:00000000 00000000  1000        0 206980 1 0000000000000000 20 0 0 10 -1
+";
+
+    const UDP: &str = "\
+   sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+ 1050: 0100007F:9A0D 0100007F:14E9 01 00000000:00000000 00:00000000 00000000     0        0 206998 2 00000000e79e0e5d 0
+ 3830: 00000000:14E9 00000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 206997 2 00000000e72ab0a9 0
+";
+
+    const UDP6: &str = "\
+  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops
+ 3831: 00000000000000000000000000000000:14EA 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000     0        0 206999 2 000000001a2451a6 0
+";
+
+    const UNIX: &str = "\
+Num       RefCount Protocol Flags    Type St Inode Path
+0000000023dda517: 00000002 00000000 00010000 0001 01 203697 /run/user/1000/bus
+00000000d498a560: 00000003 00000000 00000000 0001 03   658
+";
+
+    fn rows(protocol: Protocol, content: &str) -> Vec<(String, String, String, u64)> {
+        parse_table(protocol, content)
+            .into_iter()
+            .map(|socket| (socket.local, socket.remote, socket.state.into_owned(), socket.inode))
+            .collect()
+    }
+
+    fn row(local: &str, remote: &str, state: &str, inode: u64) -> (String, String, String, u64) {
+        (local.to_string(), remote.to_string(), state.to_string(), inode)
+    }
+
+    #[test]
+    fn parses_tcp() {
+        assert_eq!(
+            rows(Protocol::Tcp, TCP),
+            [
+                row("0.0.0.0:2024", "0.0.0.0:0", "LISTEN", 662),
+                row("127.0.0.1:8081", "0.0.0.0:0", "LISTEN", 206994),
+                row("127.0.0.1:57370", "127.0.0.1:8081", "ESTABLISHED", 206995),
+                row("10.0.2.15:22", "10.0.2.2:50000", "TIME_WAIT", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_tcp6() {
+        assert_eq!(
+            rows(Protocol::Tcp6, TCP6),
+            [
+                row("[::1]:8080", "[::]:0", "LISTEN", 206991),
+                row("[::1]:8080", "[::1]:52958", "ESTABLISHED", 206993),
+                row("[2001:db8::1]:443", "[::ffff:10.0.2.15]:54321", "CLOSE_WAIT", 206980),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_udp() {
+        assert_eq!(
+            rows(Protocol::Udp, UDP),
+            [
+                row("127.0.0.1:39437", "127.0.0.1:5353", "ESTAB", 206998),
+                row("0.0.0.0:5353", "0.0.0.0:0", "UNCONN", 206997),
+            ]
+        );
+        assert_eq!(rows(Protocol::Udp6, UDP6), [row("[::]:5354", "[::]:0", "UNCONN", 206999)]);
+    }
+
+    #[test]
+    fn parses_unix() {
+        assert_eq!(
+            rows(Protocol::Unix, UNIX),
+            [row("/run/user/1000/bus", "", "LISTEN", 203697), row("", "", "CONNECTED", 658)]
+        );
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let content = "  sl  local_address rem_address   st\n   0: 0100007F 00000000:0000 0A\n   1: 0100007F:1F91 00000000:0000 ZZ\n";
+        assert!(parse_table(Protocol::Tcp, content).is_empty());
+    }
+}
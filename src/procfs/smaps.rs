@@ -0,0 +1,290 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : procfs/smaps.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Memory mappings of a process from /proc/<pid>/smaps
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{cmp::Reverse, collections::HashMap, fs, io, path::Path};
+use serde::{Deserialize, Serialize};
+
+// All sizes are in KiB, as reported by the kernel
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub path: String,
+    pub count: usize,
+    pub size: u64,
+    pub rss: u64,
+    pub pss: u64,
+    pub swap: u64,
+    pub dirty: u64,
+}
+
+impl Mapping {
+    fn add(&mut self, other: &Mapping) {
+        self.start = self.start.min(other.start);
+        self.end = self.end.max(other.end);
+        self.count += other.count;
+        self.size += other.size;
+        self.rss += other.rss;
+        self.pss += other.pss;
+        self.swap += other.swap;
+        self.dirty += other.dirty;
+    }
+}
+
+// Whole-process totals from /proc/<pid>/smaps_rollup, in bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MemoryBreakdown {
+    pub rss: u64,
+    pub pss: u64,
+    pub uss: u64,
+    pub shared: u64,
+    pub swap: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MapSort {
+    Address,
+    Size,
+    Rss,
+    Pss,
+    Swap,
+    Dirty,
+}
+
+impl MapSort {
+    pub fn next(self) -> Self {
+        match self {
+            MapSort::Address => MapSort::Size,
+            MapSort::Size => MapSort::Rss,
+            MapSort::Rss => MapSort::Pss,
+            MapSort::Pss => MapSort::Swap,
+            MapSort::Swap => MapSort::Dirty,
+            MapSort::Dirty => MapSort::Address,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MapSort::Address => "address",
+            MapSort::Size => "size",
+            MapSort::Rss => "rss",
+            MapSort::Pss => "pss",
+            MapSort::Swap => "swap",
+            MapSort::Dirty => "dirty",
+        }
+    }
+}
+
+pub fn read_smaps(dir: &Path) -> io::Result<Vec<Mapping>> {
+    let content = fs::read_to_string(dir.join("smaps"))?;
+    Ok(parse_smaps(&content))
+}
+
+pub fn read_smaps_rollup(dir: &Path) -> Option<MemoryBreakdown> {
+    let content = fs::read_to_string(dir.join("smaps_rollup")).ok()?;
+    Some(parse_smaps_rollup(&content))
+}
+
+// Lines missing from the file count as zero
+fn parse_smaps_rollup(content: &str) -> MemoryBreakdown {
+    let mut memory = MemoryBreakdown::default();
+
+    for line in content.lines().skip(1) {
+        let mut fields = line.split_whitespace();
+        let (Some(key), Some(value)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let bytes = value.parse::<u64>().unwrap_or(0) * 1024;
+        match key {
+            "Rss:" => memory.rss = bytes,
+            "Pss:" => memory.pss = bytes,
+            "Private_Clean:" | "Private_Dirty:" => memory.uss += bytes,
+            "Shared_Clean:" | "Shared_Dirty:" => memory.shared += bytes,
+            "Swap:" => memory.swap = bytes,
+            _ => {}
+        }
+    }
+
+    memory
+}
+
+fn parse_smaps(content: &str) -> Vec<Mapping> {
+    let mut maps: Vec<Mapping> = Vec::new();
+
+    for line in content.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+
+        // Mapping header: "start-end perms offset dev inode [path]"
+        if let Some((start, end)) = first.split_once('-') {
+            if let (Ok(start), Ok(end)) = (u64::from_str_radix(start, 16), u64::from_str_radix(end, 16)) {
+                let perms = fields.next().unwrap_or("").to_string();
+                let path = header_path(line);
+                maps.push(Mapping {
+                    start,
+                    end,
+                    perms,
+                    path: if path.is_empty() { "[anon]".to_string() } else { path },
+                    count: 1,
+                    ..Mapping::default()
+                });
+                continue;
+            }
+        }
+
+        let (Some(map), Some(value)) = (maps.last_mut(), fields.next()) else {
+            continue;
+        };
+        let value: u64 = value.parse().unwrap_or(0);
+        match first {
+            "Size:" => map.size = value,
+            "Rss:" => map.rss = value,
+            "Pss:" => map.pss = value,
+            "Swap:" => map.swap = value,
+            "Shared_Dirty:" | "Private_Dirty:" => map.dirty += value,
+            _ => {}
+        }
+    }
+
+    maps
+}
+
+// What follows the five fixed fields of a mapping header, keeping any spaces inside the path
+fn header_path(line: &str) -> String {
+    let mut rest = line;
+    for _ in 0..5 {
+        rest = rest.trim_start();
+        rest = rest.find(char::is_whitespace).map_or("", |end| &rest[end..]);
+    }
+    rest.trim().to_string()
+}
+
+// Merges all mappings backed by the same file into one row
+pub fn group_by_path(maps: &[Mapping]) -> Vec<Mapping> {
+    let mut groups: HashMap<&str, Mapping> = HashMap::new();
+    for map in maps {
+        groups
+            .entry(map.path.as_str())
+            .and_modify(|group| group.add(map))
+            .or_insert_with(|| Mapping { perms: String::new(), ..map.clone() });
+    }
+    groups.into_values().collect()
+}
+
+pub fn sort_mappings(maps: &mut [Mapping], sort: MapSort) {
+    match sort {
+        MapSort::Address => maps.sort_by_key(|map| map.start),
+        MapSort::Size => maps.sort_by_key(|map| Reverse(map.size)),
+        MapSort::Rss => maps.sort_by_key(|map| Reverse(map.rss)),
+        MapSort::Pss => maps.sort_by_key(|map| Reverse(map.pss)),
+        MapSort::Swap => maps.sort_by_key(|map| Reverse(map.swap)),
+        MapSort::Dirty => maps.sort_by_key(|map| Reverse(map.dirty)),
+    }
+}
+
+pub fn totals(maps: &[Mapping]) -> Mapping {
+    let mut total = Mapping { path: "TOTAL".to_string(), ..Mapping::default() };
+    for map in maps {
+        total.count += map.count;
+        total.size += map.size;
+        total.rss += map.rss;
+        total.pss += map.pss;
+        total.swap += map.swap;
+        total.dirty += map.dirty;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMAPS: &str = "\
+55d0c8a00000-55d0c8a21000 r-xp 00000000 08:01 1048612                    /usr/bin/my  tool
+Size:                132 kB
+Rss:                 128 kB
+Pss:                  64 kB
+Shared_Clean:         64 kB
+Shared_Dirty:          0 kB
+Private_Clean:        60 kB
+Private_Dirty:         4 kB
+Swap:                  0 kB
+VmFlags: rd ex mr mw me dw
+7f3a1c000000-7f3a1c021000 rw-p 00000000 00:00 0 
+Size:                132 kB
+Rss:                  12 kB
+Pss:                  12 kB
+Private_Dirty:        12 kB
+Swap:                  8 kB
+7f3a1d000000-7f3a1d001000 rw-s 00000000 00:01 2053                       /memfd:cache (deleted)
+Size:                  4 kB
+Rss:                   4 kB
+Shared_Dirty:          4 kB
+";
+
+    #[test]
+    fn parses_mapping_headers() {
+        let maps = parse_smaps(SMAPS);
+        let paths: Vec<&str> = maps.iter().map(|map| map.path.as_str()).collect();
+        assert_eq!(paths, ["/usr/bin/my  tool", "[anon]", "/memfd:cache (deleted)"]);
+        assert_eq!((maps[0].start, maps[0].end), (0x55d0c8a00000, 0x55d0c8a21000));
+        assert_eq!(maps[0].perms, "r-xp");
+        assert_eq!(maps[2].perms, "rw-s");
+    }
+
+    #[test]
+    fn parses_mapping_sizes() {
+        let maps = parse_smaps(SMAPS);
+        assert_eq!((maps[0].size, maps[0].rss, maps[0].pss, maps[0].dirty), (132, 128, 64, 4));
+        assert_eq!((maps[1].rss, maps[1].swap, maps[1].dirty), (12, 8, 12));
+        // No Pss: line for the last mapping
+        assert_eq!((maps[2].rss, maps[2].pss, maps[2].dirty), (4, 0, 4));
+    }
+
+    #[test]
+    fn parses_rollup() {
+        let memory = parse_smaps_rollup("\
+55d0c8a00000-7ffd5b9ff000 ---p 00000000 00:00 0                          [rollup]
+Rss:                4096 kB
+Pss:                2048 kB
+Pss_Anon:           1024 kB
+Shared_Clean:       1536 kB
+Shared_Dirty:        512 kB
+Private_Clean:       256 kB
+Private_Dirty:      1792 kB
+Swap:                128 kB
+SwapPss:             128 kB
+");
+        assert_eq!(
+            memory,
+            MemoryBreakdown { rss: 4096 * 1024, pss: 2048 * 1024, uss: 2048 * 1024, shared: 2048 * 1024, swap: 128 * 1024 }
+        );
+    }
+
+    #[test]
+    fn rollup_without_pss() {
+        let memory = parse_smaps_rollup("\
+00400000-7ffd5b9ff000 ---p 00000000 00:00 0                              [rollup]
+Rss:                 100 kB
+Private_Dirty:       100 kB
+");
+        assert_eq!(memory, MemoryBreakdown { rss: 100 * 1024, uss: 100 * 1024, ..MemoryBreakdown::default() });
+    }
+}
@@ -15,84 +15,76 @@
  **********************************************************************
  */
 
-use sysinfo::{System, CpuRefreshKind, MemoryRefreshKind, RefreshKind};
+use sysinfo::System;
+use serde::{Deserialize, Serialize};
 
-pub struct SystemInfo {
-    system: System,
-    physical_core_count: usize,
+// Usage of one CPU in percent
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Core {
+    pub name: String,
+    pub usage: f32,
 }
 
-impl SystemInfo {
-    pub fn new() -> Self {
-        let mut system = System::new_with_specifics(
-            RefreshKind::new()
-                .with_cpu(CpuRefreshKind::everything())
-                .with_memory(MemoryRefreshKind::everything())
-        );
-        system.refresh_cpu();
-        
-        // Get physical core count
-        let physical_core_count = system.physical_core_count().unwrap_or(1);
-
-        Self { 
-            system,
-            physical_core_count,
-        }
+impl Core {
+    pub fn new(index: usize, usage: f32) -> Self {
+        Self { name: format!("CPU{}", index), usage }
     }
+}
 
-    pub fn update(&mut self) {
-        self.system.refresh_cpu();
-        self.system.refresh_memory();
+// CPU and memory readings taken by the collector; memory is in bytes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    cores: Vec<Core>,
+    cpu_usage: f32,
+    memory_used: u64,
+    memory_total: u64,
+}
+
+impl SystemInfo {
+    pub fn new(cores: Vec<Core>, cpu_usage: f32, (memory_used, memory_total): (u64, u64)) -> Self {
+        Self { cores, cpu_usage, memory_used, memory_total }
     }
 
-    pub fn cpu_cores_usage(&self) -> Vec<(String, f32)> {
+    // `system` must have been refreshed with CPU and memory information
+    pub fn read(system: &System, physical_core_count: usize) -> Self {
         // Get all CPU cores
-        let cpus = self.system.cpus();
-        let mut cores = Vec::with_capacity(self.physical_core_count);
+        let cpus = system.cpus();
+        let mut cores = Vec::with_capacity(physical_core_count);
 
         // Add each physical core with its proper index
-        for i in 0..self.physical_core_count {
+        for i in 0..physical_core_count {
             if let Some(cpu) = cpus.get(i) {
-                cores.push((format!("CPU{}", i), cpu.cpu_usage()));
+                cores.push(Core::new(i, cpu.cpu_usage()));
             }
         }
 
-        cores
-    }
-
-    pub fn cpu_usage(&self) -> f32 {
-        let total: f32 = self.system.cpus()
+        let total: f32 = cpus
             .iter()
-            .take(self.physical_core_count)
+            .take(physical_core_count)
             .map(|cpu| cpu.cpu_usage())
             .sum();
-        total / self.physical_core_count as f32
+
+        Self {
+            cores,
+            cpu_usage: total / physical_core_count.max(1) as f32,
+            memory_used: system.used_memory(),
+            memory_total: system.total_memory(),
+        }
+    }
+
+    pub fn cores(&self) -> &[Core] {
+        &self.cores
+    }
+
+    pub fn cpu_usage(&self) -> f32 {
+        self.cpu_usage
     }
 
     pub fn memory_usage(&self) -> (u64, u64) {
-        (self.system.used_memory(), self.system.total_memory())
+        (self.memory_used, self.memory_total)
     }
 
     pub fn load_average(&self) -> (f64, f64, f64) {
         (0.0, 0.0, 0.0) // Windows doesn't support load average
     }
 }
-
-#[derive(Clone, Debug)]
-pub struct ProcessInfo {
-    pub pid: sysinfo::Pid,
-    pub name: String,
-    pub cpu_usage: f32,
-    pub memory_usage: u64,
-    pub status: String,
-}
-
-impl ProcessInfo {
-    pub fn kill(&self) -> bool {
-        if let Some(process) = System::new_all().process(self.pid) {
-            process.kill()
-        } else {
-            false
-        }
-    }
-} 
\ No newline at end of file
@@ -0,0 +1,64 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/debug.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Debug overlay with collection and drawing times for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::time::Duration;
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::Style,
+    layout::Rect,
+    text::Line,
+};
+use crate::App;
+use super::Theme;
+
+const WIDTH: u16 = 44;
+
+// Drawn over the top right corner of `area`
+pub fn draw_debug(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let snapshot = &app.snapshot;
+    let timings = snapshot.timings;
+    let threads: usize = snapshot.threads.values().map(Vec::len).sum();
+    let ms = |duration: Duration| format!("{:>8.2} ms", duration.as_secs_f64() * 1000.0);
+
+    let lines: Vec<Line> = [
+        ("collect", ms(timings.total), format!("every {} ms", snapshot.interval.as_millis())),
+        ("  cpu/memory", ms(timings.system), String::new()),
+        ("  processes", ms(timings.processes), format!("{} + {} threads", snapshot.processes.len(), threads)),
+        ("  churn", " ".repeat(11), format!("+{} -{} processes", snapshot.started, snapshot.exited)),
+        ("  smaps", ms(timings.memory), String::new()),
+        ("  view", ms(timings.view), String::new()),
+        ("apply", ms(app.apply_time), format!("{} rows", app.process_list.processes().len())),
+        ("draw", ms(app.draw_time), String::new()),
+    ]
+    .into_iter()
+    .map(|(label, time, note)| Line::from(format!("{:<12}{} {}", label, time, note)))
+    .collect();
+
+    let width = WIDTH.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let rect = Rect::new(area.right().saturating_sub(width), area.y, width, height);
+
+    let overlay = Paragraph::new(lines)
+        .style(Style::default().fg(theme.neon_green).bg(theme.dark_bg))
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(theme.cyber_yellow))
+            .title("Debug"));
+    f.render_widget(Clear, rect);
+    f.render_widget(overlay, rect);
+}
@@ -0,0 +1,161 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/detail.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Process detail panel for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::path::PathBuf;
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::{Style, Modifier},
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+};
+use crate::{
+    App, DetailTab, View,
+    collector::ViewData,
+    process::{environment, format_duration, format_timestamp, ProcessDetail},
+};
+use super::Theme;
+
+pub fn draw_detail(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let title = match &app.detail {
+        Some(detail) => format!("Process {} ({})", detail.pid, detail.name),
+        None => "Process".to_string(),
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow))
+        .title(title);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)])
+        .split(inner);
+
+    let selected = match app.detail_tab {
+        DetailTab::Overview => 0,
+        DetailTab::Environment => 1,
+    };
+    let tabs = Tabs::new(vec!["Overview", "Environment"])
+        .select(selected)
+        .style(Style::default().fg(theme.electric_blue))
+        .highlight_style(Style::default().fg(theme.cyber_yellow).add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .block(Block::default().borders(Borders::BOTTOM).border_style(Style::default().fg(theme.light_bg)));
+    f.render_widget(tabs, chunks[0]);
+
+    let lines = match (&app.detail, app.detail_tab) {
+        // The collector has not read the process yet
+        (None, _) if !matches!(*app.snapshot.view, ViewData::Detail(pid, _) if View::Detail(pid) == app.view) => {
+            vec![Line::styled("Reading process...", Style::default().fg(theme.electric_blue))]
+        }
+        (None, _) => vec![Line::styled("Process has exited", Style::default().fg(theme.cpu_high))],
+        (Some(detail), DetailTab::Overview) => detail_lines(detail, theme),
+        (Some(detail), DetailTab::Environment) => environment_lines(detail, app, theme),
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((app.view_scroll, 0));
+    f.render_widget(paragraph, chunks[1]);
+}
+
+fn environment_lines<'a>(detail: &ProcessDetail, app: &App, theme: &Theme) -> Vec<Line<'a>> {
+    let vars = environment(&detail.environ, &app.secret_patterns, &app.view_query);
+    let hidden = vars.iter().filter(|var| var.secret).count();
+
+    let status = if app.view_searching || !app.view_query.is_empty() {
+        format!("Search: {}{}", app.view_query, if app.view_searching { "_" } else { "" })
+    } else if app.reveal_secrets {
+        format!("{} variables, secrets revealed (r to hide)", vars.len())
+    } else {
+        format!("{} variables, {} secret value(s) masked (r to reveal)", vars.len(), hidden)
+    };
+
+    let mut lines = vec![Line::styled(status, Style::default().fg(theme.neon_pink)), Line::default()];
+    lines.extend(vars.iter().map(|var| {
+        let value_style = if var.secret {
+            Style::default().fg(theme.cpu_medium)
+        } else {
+            Style::default().fg(theme.neon_green)
+        };
+        Line::from(vec![
+            Span::styled(var.key.clone(), Style::default().fg(theme.electric_blue)),
+            Span::styled("=", Style::default().fg(theme.electric_blue)),
+            Span::styled(var.display_value(app.reveal_secrets).to_string(), value_style),
+        ])
+    }));
+    lines
+}
+
+fn detail_lines<'a>(detail: &'a ProcessDetail, theme: &Theme) -> Vec<Line<'a>> {
+    let field = |label: &'a str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<12}", label), Style::default().fg(theme.electric_blue)),
+            Span::styled(value, Style::default().fg(theme.neon_green)),
+        ])
+    };
+    let section = |title: &'a str| {
+        Line::styled(title, Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD))
+    };
+    let item = |value: String| Line::styled(format!("  {}", value), Style::default().fg(theme.neon_green));
+    let path = |path: &Option<PathBuf>| {
+        path.as_ref()
+            .map_or_else(|| "<unavailable>".to_string(), |p| p.display().to_string())
+    };
+    let ids = |ids: [u32; 3]| format!("real {}  effective {}  saved {}", ids[0], ids[1], ids[2]);
+    let optional = |value: Option<i64>| value.map_or_else(|| "-".to_string(), |v| v.to_string());
+
+    let mut lines = vec![
+        field("Executable", path(&detail.exe)),
+        field("Cwd", path(&detail.cwd)),
+        field("Root", path(&detail.root)),
+        field("User", detail.user.clone().unwrap_or_else(|| "-".to_string())),
+    ];
+    if let Some(credentials) = detail.credentials {
+        lines.push(field("Uid", ids(credentials.uid)));
+        lines.push(field("Gid", ids(credentials.gid)));
+    }
+    lines.extend([
+        field("State", detail.state.clone()),
+        field("Priority", optional(detail.priority)),
+        field("Nice", optional(detail.nice)),
+        field("Started", format_timestamp(detail.start_time)),
+        field("Running", format_duration(detail.run_time)),
+        Line::default(),
+        section("Command line"),
+        item(detail.cmdline.join(" ")),
+        Line::default(),
+        section("Parent chain"),
+    ]);
+
+    lines.extend(detail.parents.iter().map(|(pid, name)| item(format!("{} ({})", pid, name))));
+
+    lines.push(Line::default());
+    lines.push(section("Cgroups"));
+    lines.extend(detail.cgroups.iter().map(|cgroup| item(cgroup.clone())));
+
+    lines.push(Line::default());
+    lines.push(section("Namespaces"));
+    lines.extend(detail.namespaces.iter().map(|(kind, target)| item(format!("{:<18} {}", kind, target))));
+
+    lines.push(Line::default());
+    lines.push(section("Limits"));
+    lines.extend(detail.limits.iter().map(|limit| item(limit.clone())));
+
+    lines
+}
@@ -0,0 +1,85 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/files.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Open files and sockets panel for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::{Style, Modifier},
+    layout::{Constraint, Rect},
+};
+use sysinfo::Pid;
+use crate::procfs::{describe_flags, FdKind, FileDescriptor};
+use super::Theme;
+
+pub fn draw_files(
+    f: &mut Frame,
+    area: Rect,
+    pid: Pid,
+    files: &Result<Vec<FileDescriptor>, String>,
+    scroll: u16,
+    theme: &Theme,
+) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow));
+
+    let files = match files {
+        Ok(files) => files,
+        Err(err) => {
+            let message = Paragraph::new(err.as_str())
+                .style(Style::default().fg(theme.cpu_high))
+                .block(block.title(format!("Open files of {}", pid)))
+                .wrap(Wrap { trim: false });
+            f.render_widget(message, area);
+            return;
+        }
+    };
+
+    let header = Row::new(["FD", "TYPE", "FLAGS", "POS", "TARGET"])
+        .style(Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = files
+        .iter()
+        .skip((scroll as usize).min(files.len().saturating_sub(1)))
+        .map(|file| {
+            let color = match file.kind {
+                FdKind::Socket => theme.electric_blue,
+                FdKind::Pipe | FdKind::AnonInode => theme.cyber_yellow,
+                _ => theme.neon_green,
+            };
+            Row::new([
+                file.fd.to_string(),
+                file.kind.to_string(),
+                file.flags.map_or_else(|| "-".to_string(), describe_flags),
+                file.pos.map_or_else(|| "-".to_string(), |pos| pos.to_string()),
+                file.target.clone(),
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let table = Table::new(rows, [
+        Constraint::Length(6),
+        Constraint::Length(10),
+        Constraint::Length(30),
+        Constraint::Length(12),
+        Constraint::Min(0),
+    ])
+    .header(header)
+    .block(block.title(format!("Open files of {} ({} descriptors)", pid, files.len())));
+    f.render_widget(table, area);
+}
@@ -0,0 +1,64 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/log.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Log screen for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::{Style, Modifier},
+    layout::{Constraint, Rect},
+};
+use tracing::Level;
+use crate::App;
+use super::Theme;
+
+pub fn draw_log(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let lines = app.logs.lines();
+    let title = match &app.log_path {
+        Some(path) => format!("Log ({} lines, newest first, also in {})", lines.len(), path.display()),
+        None => format!("Log ({} lines, newest first)", lines.len()),
+    };
+
+    let header = Row::new(["TIME", "LEVEL", "MESSAGE"])
+        .style(Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = lines
+        .iter()
+        .skip((app.view_scroll as usize).min(lines.len().saturating_sub(1)))
+        .map(|line| {
+            let color = match line.level {
+                Level::ERROR => theme.cpu_high,
+                Level::WARN => theme.cpu_medium,
+                Level::INFO => theme.neon_green,
+                _ => theme.electric_blue,
+            };
+            Row::new([line.time.clone(), line.level.to_string(), line.message.clone()])
+                .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let table = Table::new(rows, [
+        Constraint::Length(8),
+        Constraint::Length(5),
+        Constraint::Min(0),
+    ])
+    .header(header)
+    .block(Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow))
+        .title(title));
+    f.render_widget(table, area);
+}
@@ -0,0 +1,119 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/maps.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Memory map panel for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::{Style, Modifier},
+    layout::{Constraint, Direction, Layout, Rect},
+};
+use sysinfo::Pid;
+use crate::{
+    App,
+    procfs::{group_by_path, sort_mappings, totals, Mapping},
+};
+use super::Theme;
+
+const WIDTHS: [Constraint; 7] = [
+    Constraint::Length(28),
+    Constraint::Length(5),
+    Constraint::Length(10),
+    Constraint::Length(10),
+    Constraint::Length(10),
+    Constraint::Length(10),
+    Constraint::Min(0),
+];
+
+pub fn draw_maps(f: &mut Frame, area: Rect, pid: Pid, app: &App, theme: &Theme) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow));
+
+    let maps = match &app.maps {
+        Ok(maps) => maps,
+        Err(err) => {
+            let message = Paragraph::new(err.as_str())
+                .style(Style::default().fg(theme.cpu_high))
+                .block(block.title(format!("Memory map of {}", pid)))
+                .wrap(Wrap { trim: false });
+            f.render_widget(message, area);
+            return;
+        }
+    };
+
+    let mut rows = if app.map_grouped { group_by_path(maps) } else { maps.clone() };
+    sort_mappings(&mut rows, app.map_sort);
+    let total = totals(maps);
+
+    let title = format!(
+        "Memory map of {} ({} mappings, sorted by {}{})",
+        pid,
+        maps.len(),
+        app.map_sort.label(),
+        if app.map_grouped { ", grouped by file" } else { "" }
+    );
+    let inner = block.inner(area);
+    f.render_widget(block.title(title), area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(inner);
+
+    let header = Row::new(["ADDRESS", "PERM", "SIZE", "RSS", "PSS", "SWAP", "FILE (dirty)"])
+        .style(Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD));
+
+    let skip = (app.view_scroll as usize).min(rows.len().saturating_sub(1));
+    let body: Vec<Row> = rows
+        .iter()
+        .skip(skip)
+        .map(|map| mapping_row(map, app.map_grouped).style(Style::default().fg(theme.neon_green)))
+        .collect();
+
+    f.render_widget(Table::new(body, WIDTHS).header(header), chunks[0]);
+
+    let footer = Table::new([mapping_row(&total, true)], WIDTHS)
+        .style(Style::default().fg(theme.cyber_yellow).add_modifier(Modifier::BOLD));
+    f.render_widget(footer, chunks[1]);
+}
+
+fn mapping_row(map: &Mapping, grouped: bool) -> Row<'static> {
+    let address = if grouped {
+        format!("{} mapping(s)", map.count)
+    } else {
+        format!("{:012x}-{:012x}", map.start, map.end)
+    };
+    Row::new([
+        address,
+        map.perms.clone(),
+        format_kib(map.size),
+        format_kib(map.rss),
+        format_kib(map.pss),
+        format_kib(map.swap),
+        format!("{} ({})", map.path, format_kib(map.dirty)),
+    ])
+}
+
+fn format_kib(kib: u64) -> String {
+    if kib >= 1024 * 1024 {
+        format!("{:.1}G", kib as f64 / (1024.0 * 1024.0))
+    } else if kib >= 1024 {
+        format!("{:.1}M", kib as f64 / 1024.0)
+    } else {
+        format!("{}K", kib)
+    }
+}
@@ -15,6 +15,7 @@
  **********************************************************************
  */
 
+use std::ops::Range;
 use ratatui::{
     Frame,
     widgets::*,
@@ -23,38 +24,53 @@ use ratatui::{
     prelude::Alignment,
     text::{Line, Span},
 };
-use crate::{App, event::InputMode};
-
-// Cyberpunk color theme
-pub struct CyberpunkTheme {
-    pub neon_pink: Color,
-    pub electric_blue: Color,
-    pub cyber_yellow: Color,
-    pub neon_green: Color,
-    pub dark_bg: Color,
-    pub light_bg: Color,
-    pub cpu_low: Color,
-    pub cpu_medium: Color,
-    pub cpu_high: Color,
+use crate::{
+    App, DetailTab, View,
+    event::InputMode,
+    keymap::Action,
+    process::{format_timestamp, Column, ProcessKind, SortField, SortOrder},
+    system::{Core, SystemInfo},
+};
+
+mod debug;
+mod detail;
+mod files;
+mod log;
+mod maps;
+mod network;
+mod rows;
+mod theme;
+
+pub use rows::RowCache;
+pub use theme::{names as theme_names, ColorDepth, Theme};
+
+// Meters of the header area, shown in the configured order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Meter {
+    Cores,
+    Cpu,
+    Memory,
+    Load,
 }
 
-impl Default for CyberpunkTheme {
-    fn default() -> Self {
-        Self {
-            neon_pink: Color::Rgb(255, 0, 153),
-            electric_blue: Color::Rgb(0, 255, 255),
-            cyber_yellow: Color::Rgb(255, 255, 0),
-            neon_green: Color::Rgb(0, 255, 128),
-            dark_bg: Color::Rgb(13, 2, 33),
-            light_bg: Color::Rgb(30, 11, 66),
-            cpu_low: Color::Rgb(0, 255, 0),      // Green for low usage
-            cpu_medium: Color::Rgb(255, 165, 0),  // Orange for medium usage
-            cpu_high: Color::Rgb(255, 0, 0),      // Red for high usage
+impl Meter {
+    pub const ALL: [Meter; 4] = [Meter::Cores, Meter::Cpu, Meter::Memory, Meter::Load];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Meter::Cores => "cores",
+            Meter::Cpu => "cpu",
+            Meter::Memory => "memory",
+            Meter::Load => "load",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|meter| meter.name() == name)
+    }
 }
 
-fn get_cpu_color(usage: f32, theme: &CyberpunkTheme) -> Color {
+fn get_cpu_color(usage: f32, theme: &Theme) -> Color {
     if usage < 50.0 {
         theme.cpu_low
     } else if usage < 80.0 {
@@ -64,10 +80,9 @@ fn get_cpu_color(usage: f32, theme: &CyberpunkTheme) -> Color {
     }
 }
 
-fn draw_cpu_gauges(f: &mut Frame, area: Rect, cpu_cores: &[(String, f32)], theme: &CyberpunkTheme) {
-    // Calculate how many rows we need (each row will have 3 CPU cores)
-    let cores_per_row = 3;
-    let rows = (cpu_cores.len() + cores_per_row - 1) / cores_per_row;
+fn draw_cpu_gauges(f: &mut Frame, area: Rect, cpu_cores: &[Core], cores_per_row: usize, theme: &Theme) {
+    // Calculate how many rows we need
+    let rows = cpu_cores.len().div_ceil(cores_per_row);
     
     // Create vertical layout for rows
     let row_constraints = vec![Constraint::Length(1); rows];
@@ -95,26 +110,19 @@ fn draw_cpu_gauges(f: &mut Frame, area: Rect, cpu_cores: &[(String, f32)], theme
             .split(row_layout[row]);
 
         // Draw each CPU core in this row
-        for (i, (name, usage)) in cores_in_this_row.iter().enumerate() {
-            let bar_width = 20;
-            let filled = ((usage * bar_width as f32) / 100.0) as usize;
-            let bar: String = format!(
-                "{}{}",
-                "█".repeat(filled),
-                "░".repeat(bar_width - filled)
-            );
-            
+        for (i, core) in cores_in_this_row.iter().enumerate() {
+            let (bar, label) = core_gauge(core.usage);
             let line = Line::from(vec![
                 Span::styled(
-                    format!("{:<4}", name),
+                    format!("{:<4}", core.name),
                     Style::default().fg(theme.electric_blue)
                 ),
                 Span::styled(
                     bar,
-                    Style::default().fg(get_cpu_color(*usage, theme))
+                    Style::default().fg(get_cpu_color(core.usage, theme))
                 ),
                 Span::styled(
-                    format!("{:>5.1}%", usage),
+                    label,
                     Style::default().fg(theme.neon_green)
                 ),
             ]);
@@ -126,16 +134,64 @@ fn draw_cpu_gauges(f: &mut Frame, area: Rect, cpu_cores: &[(String, f32)], theme
     }
 }
 
-pub fn draw(f: &mut Frame, app: &App) {
-    let theme = CyberpunkTheme::default();
+// Screen positions of the last frame, used to map mouse clicks
+#[derive(Debug, Default)]
+pub struct Regions {
+    header: Rect,
+    rows: Rect,
+    // None while a secondary view hides the process list
+    offset: Option<usize>,
+    columns: Vec<(u16, u16, Column)>,
+    status: Rect,
+    buttons: Vec<(u16, u16, Action)>,
+}
+
+impl Regions {
+    pub fn column_at(&self, x: u16, y: u16) -> Option<Column> {
+        if y != self.header.y || self.header.height == 0 {
+            return None;
+        }
+        self.columns.iter().find(|(start, end, _)| (*start..*end).contains(&x)).map(|(_, _, column)| *column)
+    }
+
+    // Index into the process list of the row at the given position
+    pub fn row_at(&self, x: u16, y: u16) -> Option<usize> {
+        let inside = (self.rows.x..self.rows.right()).contains(&x) && (self.rows.y..self.rows.bottom()).contains(&y);
+        inside.then(|| self.offset.unwrap_or(0) + (y - self.rows.y) as usize)
+    }
+
+    // First process list row on screen, if the list was drawn
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    // Indices of the process list rows that fit on screen, some possibly past its end
+    pub fn visible_rows(&self) -> Range<usize> {
+        let offset = self.offset.unwrap_or(0);
+        offset..offset + self.rows.height as usize
+    }
+
+    pub fn button_at(&self, x: u16, y: u16) -> Option<Action> {
+        if y != self.status.y || self.status.height == 0 {
+            return None;
+        }
+        self.buttons.iter().find(|(start, end, _)| (*start..*end).contains(&x)).map(|(_, _, action)| *action)
+    }
+}
+
+pub fn draw(f: &mut Frame, app: &App, rows: &mut RowCache) -> Regions {
+    let theme = &app.theme;
+    let mut regions = Regions::default();
+    let show_cores = app.meters.contains(&Meter::Cores);
+    let show_stats = app.meters.iter().any(|meter| *meter != Meter::Cores);
     
     // Create the layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),  // Header
-            Constraint::Length(8),  // CPU cores (increased height for more rows)
-            Constraint::Length(3),  // System stats
+            Constraint::Length(if show_cores { 8 } else { 0 }),  // CPU cores (increased height for more rows)
+            Constraint::Length(if show_stats { 3 } else { 0 }),  // System stats
             Constraint::Min(0),     // Process list
             Constraint::Length(1),  // Status bar
         ])
@@ -145,29 +201,36 @@ pub fn draw(f: &mut Frame, app: &App) {
     let header = Paragraph::new("BlackWin htop - Advanced Windows Process Monitor ::: Crafted with precision by Ebrahim Shafiei (EbraSha)")
         .style(Style::default()
             .fg(theme.neon_pink)
+            .bg(theme.dark_bg)
             .add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center);
     f.render_widget(header, chunks[0]);
 
+    if app.view != View::Processes {
+        let area = chunks[1].union(chunks[3]);
+        match app.view {
+            View::Detail(_) => detail::draw_detail(f, area, app, theme),
+            View::Files(pid) => files::draw_files(f, area, pid, &app.files, app.view_scroll, theme),
+            View::MemoryMap(pid) => maps::draw_maps(f, area, pid, app, theme),
+            View::Network => network::draw_network(f, area, app, theme),
+            View::Log => log::draw_log(f, area, app, theme),
+            View::Processes => {}
+        }
+        if app.show_debug {
+            debug::draw_debug(f, area, app, theme);
+        }
+        draw_status_bar(f, chunks[4], app, theme, &mut regions);
+        return regions;
+    }
+
     // Draw CPU cores
-    let cpu_cores = app.system_info.cpu_cores_usage();
-    draw_cpu_gauges(f, chunks[1], &cpu_cores, &theme);
+    if show_cores {
+        let cpu_cores = app.snapshot.system.cores();
+        draw_cpu_gauges(f, chunks[1], cpu_cores, app.meter_columns, theme);
+    }
 
     // Draw system stats
-    let (used_mem, total_mem) = app.system_info.memory_usage();
-    let mem_percentage = (used_mem as f64 / total_mem as f64 * 100.0) as u64;
-    let cpu_usage = app.system_info.cpu_usage();
-    let (load1, load5, load15) = app.system_info.load_average();
-
-    let stats = format!(
-        "CPU: {:.1}% | Mem: {}/{}MB ({:.1}%) | Load: {:.2} {:.2} {:.2}",
-        cpu_usage,
-        used_mem / 1024 / 1024,
-        total_mem / 1024 / 1024,
-        mem_percentage,
-        load1, load5, load15
-    );
-
+    let stats = stats_line(&app.meters, &app.snapshot.system);
     let stats_widget = Paragraph::new(stats)
         .style(Style::default().fg(theme.electric_blue))
         .block(Block::default()
@@ -176,76 +239,290 @@ pub fn draw(f: &mut Frame, app: &App) {
             .title("System Stats"));
     f.render_widget(stats_widget, chunks[2]);
 
-    // Draw process list
-    let processes = app.process_list.processes();
-    let items: Vec<ListItem> = processes
+    draw_process_list(f, chunks[3], app, theme, rows, &mut regions);
+    if app.show_debug {
+        debug::draw_debug(f, chunks[3], app, theme);
+    }
+
+    draw_status_bar(f, chunks[4], app, theme, &mut regions);
+    regions
+}
+
+// Bar and percentage of one core's gauge
+fn core_gauge(usage: f32) -> (String, String) {
+    let bar_width = 20;
+    let filled = (((usage * bar_width as f32) / 100.0) as usize).min(bar_width);
+    let bar = format!("{}{}", "█".repeat(filled), "░".repeat(bar_width.saturating_sub(filled)));
+    (bar, format!("{:>5.1}%", usage))
+}
+
+// The header meters as drawn, so that readings which do not change them need no redraw
+pub fn meters_shown(meters: &[Meter], system: &SystemInfo) -> Vec<String> {
+    let mut shown = Vec::new();
+    if meters.contains(&Meter::Cores) {
+        for core in system.cores() {
+            let (bar, label) = core_gauge(core.usage);
+            shown.push(format!("{} {}{}", core.name, bar, label));
+        }
+    }
+    shown.push(stats_line(meters, system));
+    shown
+}
+
+// The text meters in the given order, as in the System Stats box
+pub fn stats_line(meters: &[Meter], system: &SystemInfo) -> String {
+    meters
         .iter()
-        .enumerate()
-        .map(|(i, process)| {
-            let content = format!(
-                "{:>6} {:>8.1}% {:>8}MB {}",
-                process.pid,
-                process.cpu_usage,
-                process.memory_usage / 1024 / 1024,
-                process.name,
-            );
-            let style = if i == app.process_list.selected_index() {
-                Style::default()
-                    .fg(theme.cyber_yellow)
-                    .add_modifier(Modifier::REVERSED)
-            } else {
-                Style::default().fg(theme.neon_green)
-            };
-            ListItem::new(content).style(style)
+        .filter_map(|meter| match meter {
+            Meter::Cores => None,
+            Meter::Cpu => Some(format!("CPU: {:.1}%", system.cpu_usage())),
+            Meter::Memory => {
+                let (used_mem, total_mem) = system.memory_usage();
+                let mem_percentage = (used_mem as f64 / total_mem as f64 * 100.0) as u64;
+                Some(format!(
+                    "Mem: {}/{}MB ({:.1}%)",
+                    used_mem / 1024 / 1024,
+                    total_mem / 1024 / 1024,
+                    mem_percentage
+                ))
+            }
+            Meter::Load => {
+                let (load1, load5, load15) = system.load_average();
+                Some(format!("Load: {:.2} {:.2} {:.2}", load1, load5, load15))
+            }
         })
-        .collect();
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
 
-    let process_list = List::new(items)
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .border_style(Style::default().fg(theme.cyber_yellow))
-            .title("Processes"))
-        .highlight_style(
+fn draw_process_list(f: &mut Frame, area: Rect, app: &App, theme: &Theme, rows: &mut RowCache, regions: &mut Regions) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow))
+        .title(process_list_title(app));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+
+    // Column headers, offset by the width of the highlight symbol
+    let sort_column = match app.process_list.sort_field() {
+        SortField::Pid => Column::Pid,
+        SortField::Name => Column::Name,
+        SortField::Cpu => Column::Cpu,
+        SortField::Memory(kind) => Column::Memory(kind),
+    };
+    let mut header = vec![Span::raw("   ")];
+    let mut x = chunks[0].x + 3;
+    for column in &app.columns {
+        let style = if *column == sort_column {
+            Style::default().fg(theme.cyber_yellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD)
+        };
+        let cell = column.cell(column.header());
+        // The name column reaches to the right border
+        let end = match column {
+            Column::Name => chunks[0].right(),
+            _ => x + cell.chars().count() as u16,
+        };
+        regions.columns.push((x, end, *column));
+        x = end + 1;
+        header.push(Span::styled(cell, style));
+        header.push(Span::raw(" "));
+    }
+    regions.header = chunks[0];
+    f.render_widget(Paragraph::new(Line::from(header)), chunks[0]);
+
+    // Only the rows on screen are drawn, so the cost does not grow with the number of processes
+    let processes = app.process_list.processes();
+    let area = chunks[1];
+    let height = area.height as usize;
+    let selected_index = app.process_list.selected_index();
+    let offset = list_offset(app.list_offset, selected_index, height, processes.len());
+    let end = (offset + height).min(processes.len());
+
+    rows.start_frame();
+    let buf = f.buffer_mut();
+    for (i, process) in processes[offset..end].iter().enumerate() {
+        let y = area.y + i as u16;
+        let selected = offset + i == selected_index;
+        let style = if selected {
             Style::default()
                 .fg(theme.cyber_yellow)
                 .add_modifier(Modifier::REVERSED)
-        )
-        .highlight_symbol(">> ");
-    
-    // Calculate scroll offset to keep selected item in view
-    let list_height = chunks[3].height as usize - 2; // Subtract 2 for borders
-    let selected_index = app.process_list.selected_index();
-    let scroll_threshold = list_height / 4;
-    let scroll = if selected_index > list_height - scroll_threshold {
-        selected_index - (list_height - scroll_threshold)
+        } else if process.kind == ProcessKind::Thread {
+            Style::default().fg(theme.electric_blue)
+        } else {
+            Style::default().fg(theme.neon_green)
+        };
+        buf.set_style(Rect::new(area.x, y, area.width, 1), style);
+        let (x, _) = buf.set_stringn(area.x, y, if selected { ">> " } else { "   " }, area.width as usize, style);
+        let width = area.right().saturating_sub(x) as usize;
+        buf.set_stringn(x, y, rows.text(process, &app.columns), width, style);
+    }
+
+    regions.rows = area;
+    regions.offset = Some(offset);
+}
+
+// Keeps the previous offset unless the selection left the screen, then scrolls just enough
+fn list_offset(previous: usize, selected: usize, height: usize, len: usize) -> usize {
+    let offset = if selected < previous {
+        selected
+    } else if height > 0 && selected >= previous + height {
+        selected + 1 - height
     } else {
-        0
+        previous
     };
+    // No empty space below the last row after the list shrank
+    offset.min(len.saturating_sub(height))
+}
 
-    let list_state = &mut ListState::default();
-    list_state.select(Some(selected_index));
-    
-    f.render_stateful_widget(process_list, chunks[3], list_state);
+fn draw_status_bar(f: &mut Frame, area: Rect, app: &App, theme: &Theme, regions: &mut Regions) {
+    let keys = |action| app.keymap.label(action).unwrap_or_else(|| "-".to_string());
+    let close = (format!("{}/{}-Close", keys(Action::CloseView), keys(Action::Quit)), Some(Action::Quit));
+    let scroll = (format!("{}/{}-Scroll", keys(Action::Down), keys(Action::Up)), None);
+    let page = (format!("{}/{}-Page", keys(Action::PageUp), keys(Action::PageDown)), None);
+    let text = |text: &str| (text.to_string(), None);
 
-    // Draw status bar
-    let status = match app.input_state.mode {
+    // Help entries bound to an action can be clicked
+    let entries: Vec<(String, Option<Action>)> = match app.input_state.mode {
+        _ if app.message.is_some() => vec![text(app.message.as_deref().unwrap_or_default())],
+        _ if app.view_searching => {
+            vec![text(&format!("Search: {} (Press Enter to confirm, Esc to cancel)", app.view_query))]
+        }
+        _ if matches!(app.view, View::Detail(_)) && app.detail_tab == DetailTab::Environment => {
+            vec![
+                close,
+                text(&format!("{}-Overview", keys(Action::NextTab))),
+                text(&format!("{}-Search", keys(Action::ViewSearch))),
+                text(&format!("{}-Reveal/hide secrets", keys(Action::RevealSecrets))),
+                scroll,
+            ]
+        }
+        _ if matches!(app.view, View::Detail(_)) => {
+            vec![close, text(&format!("{}-Environment", keys(Action::NextTab))), scroll, page]
+        }
+        _ if app.view == View::Network => vec![
+            close,
+            text(&format!("{}/{}-Move", keys(Action::Down), keys(Action::Up))),
+            text(&format!("{}-Search", keys(Action::ViewSearch))),
+            text(&format!("{}-Jump to process", keys(Action::Details))),
+        ],
+        _ if matches!(app.view, View::MemoryMap(_)) => {
+            vec![
+                close,
+                scroll,
+                page,
+                text(&format!("{}-Sort", keys(Action::SortMappings))),
+                text(&format!("{}-Group by file", keys(Action::GroupMappings))),
+            ]
+        }
+        _ if app.view != View::Processes => vec![close, scroll, page],
         InputMode::Normal => {
-            format!(
-                "Press: q-Quit | F3-Search | F9-Kill | j/k-Move | c-CPU | m-Memory | n-Name | p-PID"
-            )
+            let help = [
+                (Action::Quit, "Quit"),
+                (Action::Details, "Details"),
+                (Action::Environment, "Environment"),
+                (Action::Files, "Files"),
+                (Action::MemoryMap, "Memory map"),
+                (Action::Network, "Ports"),
+                (Action::Log, "Log"),
+                (Action::Search, "Search"),
+                (Action::Kill, "Kill"),
+                (Action::Pause, "Pause"),
+                (Action::Export, "Export"),
+                (Action::Down, "Move"),
+                (Action::SortCpu, "CPU"),
+                (Action::SortMemory, "Memory (cycle RSS/PSS/USS/SHR/SWAP)"),
+                (Action::SortName, "Name"),
+                (Action::SortPid, "PID"),
+                (Action::InvertSort, "Invert"),
+                (Action::ToggleTree, "Tree"),
+                (Action::NextTheme, "Theme"),
+                (Action::ToggleThreads, "Threads"),
+                (Action::ToggleKernelThreads, "Kthreads"),
+            ];
+            help.iter()
+                .filter_map(|(action, label)| match action {
+                    Action::Down => Some((format!("{}/{}-{}", keys(Action::Down), keys(Action::Up), label), None)),
+                    _ => Some((format!("{}-{}", app.keymap.label(*action)?, label), Some(*action))),
+                })
+                .collect()
         }
         InputMode::Search => {
-            format!("Search: {} (Press Enter to confirm, Esc to cancel)", app.input_state.search_input)
+            vec![text(&format!("Search: {} (Press Enter to confirm, Esc to cancel)", app.input_state.search_input))]
         }
     };
+    let prefix = if app.message.is_none() && entries.len() > 1 { "Press: " } else { "" };
 
     let status_style = match app.input_state.mode {
+        _ if app.message.is_some() => Style::default().fg(theme.cyber_yellow),
         InputMode::Normal => Style::default().fg(theme.neon_green),
         InputMode::Search => Style::default().fg(theme.neon_pink),
-    };
+    }
+    .bg(theme.light_bg);
 
-    let status_widget = Paragraph::new(status)
+    let mut x = area.x + prefix.chars().count() as u16;
+    let mut spans = vec![Span::raw(prefix)];
+    for (i, (label, action)) in entries.into_iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(" | "));
+            x = x.saturating_add(3);
+        }
+        let end = x.saturating_add(label.chars().count() as u16);
+        if let Some(action) = action {
+            regions.buttons.push((x, end, action));
+        }
+        x = end;
+        spans.push(Span::raw(label));
+    }
+    regions.status = area;
+
+    let status_widget = Paragraph::new(Line::from(spans))
         .style(status_style)
         .alignment(Alignment::Left);
-    f.render_widget(status_widget, chunks[4]);
-} 
\ No newline at end of file
+    f.render_widget(status_widget, area);
+}
+
+fn process_list_title(app: &App) -> String {
+    let arrow = match app.process_list.sort_order() {
+        SortOrder::Ascending => "▲",
+        SortOrder::Descending => "▼",
+    };
+    let mut title = format!("Processes {}", arrow);
+    if app.process_list.tree() {
+        title.push_str(" [tree]");
+    }
+    if app.read_only {
+        title.push_str(" [read-only]");
+    }
+    if app.process_list.show_threads() {
+        title.push_str(" [threads]");
+    }
+    if app.process_list.hide_kernel_threads() {
+        title.push_str(" [kthreads hidden]");
+    }
+    if app.paused {
+        title.push_str(&format!(" [PAUSED at {}]", format_timestamp(app.snapshot.time)));
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn core_gauge_stays_within_its_width() {
+        for usage in [0.0, 42.0, 100.0, 150.0] {
+            let (bar, _) = core_gauge(usage);
+            assert_eq!(bar.chars().count(), 20);
+        }
+        assert_eq!(core_gauge(150.0).0, "█".repeat(20));
+    }
+}
@@ -0,0 +1,85 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/network.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Listening ports and connections screen for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use ratatui::{
+    Frame,
+    widgets::*,
+    style::{Style, Modifier},
+    layout::{Constraint, Rect},
+};
+use sysinfo::Pid;
+use crate::App;
+use super::Theme;
+
+pub fn draw_network(f: &mut Frame, area: Rect, app: &App, theme: &Theme) {
+    let connections = app.visible_connections();
+    let listening = connections
+        .iter()
+        .filter(|connection| connection.socket.state == "LISTEN")
+        .count();
+
+    let mut title = format!("Ports and connections ({} sockets, {} listening", connections.len(), listening);
+    if !app.view_query.is_empty() {
+        title.push_str(&format!(", matching \"{}\"", app.view_query));
+    }
+    title.push(')');
+
+    let header = Row::new(["PROTO", "LOCAL", "REMOTE", "STATE", "PID", "PROCESS"])
+        .style(Style::default().fg(theme.neon_pink).add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = connections
+        .iter()
+        .map(|connection| {
+            let socket = &connection.socket;
+            let (pid, name) = match connection.pid {
+                Some(pid) => (
+                    pid.to_string(),
+                    app.process_list.name_of(Pid::from_u32(pid)).unwrap_or("?").to_string(),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            let color = if socket.state == "LISTEN" { theme.electric_blue } else { theme.neon_green };
+            Row::new([
+                socket.protocol.to_string(),
+                socket.local.clone(),
+                socket.remote.clone(),
+                socket.state.to_string(),
+                pid,
+                name,
+            ])
+            .style(Style::default().fg(color))
+        })
+        .collect();
+
+    let table = Table::new(rows, [
+        Constraint::Length(6),
+        Constraint::Percentage(30),
+        Constraint::Percentage(25),
+        Constraint::Length(12),
+        Constraint::Length(8),
+        Constraint::Min(0),
+    ])
+    .header(header)
+    .block(Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.cyber_yellow))
+        .title(title))
+    .highlight_style(Style::default().fg(theme.cyber_yellow).add_modifier(Modifier::REVERSED));
+
+    let mut state = TableState::default().with_selected(Some(app.view_selected));
+    f.render_stateful_widget(table, area, &mut state);
+}
@@ -0,0 +1,43 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/rows.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Formatted process list rows kept between frames for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{collections::HashMap, mem};
+use sysinfo::Pid;
+use crate::process::{format_row, Column, Process};
+
+// Text of the rows on screen, so a row is only formatted again when its process changed
+#[derive(Debug, Default)]
+pub struct RowCache {
+    rows: HashMap<Pid, (Process, String)>,
+    previous: HashMap<Pid, (Process, String)>,
+}
+
+impl RowCache {
+    // Called before the visible rows are looked up; rows not looked up again are dropped
+    pub(super) fn start_frame(&mut self) {
+        self.previous = mem::take(&mut self.rows);
+    }
+
+    pub(super) fn text(&mut self, process: &Process, columns: &[Column]) -> &str {
+        let row = self
+            .previous
+            .remove(&process.pid)
+            .filter(|(cached, _)| cached == process)
+            .unwrap_or_else(|| (process.clone(), format_row(process, columns)));
+        &self.rows.entry(process.pid).or_insert(row).1
+    }
+}
@@ -0,0 +1,302 @@
+/*
+ **********************************************************************
+ * -------------------------------------------------------------------
+ * Project Name : BlackWin htop
+ * File Name    : ui/theme.rs
+ * Author       : Ebrahim Shafiei (EbraSha)
+ * Email        : Prof.Shafiei@Gmail.com
+ * Created On   : 2024-03-17 12:00:00
+ * Description  : Color themes and terminal color support for BlackWin htop
+ * -------------------------------------------------------------------
+ *
+ * "Coding is an engaging and beloved hobby for me. I passionately and insatiably pursue knowledge in cybersecurity and programming."
+ * – Ebrahim Shafiei
+ *
+ **********************************************************************
+ */
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::Color;
+use toml::Table;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub neon_pink: Color,
+    pub electric_blue: Color,
+    pub cyber_yellow: Color,
+    pub neon_green: Color,
+    pub dark_bg: Color,
+    pub light_bg: Color,
+    pub cpu_low: Color,
+    pub cpu_medium: Color,
+    pub cpu_high: Color,
+}
+
+// Cyberpunk color theme
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            neon_pink: Color::Rgb(255, 0, 153),
+            electric_blue: Color::Rgb(0, 255, 255),
+            cyber_yellow: Color::Rgb(255, 255, 0),
+            neon_green: Color::Rgb(0, 255, 128),
+            dark_bg: Color::Rgb(13, 2, 33),
+            light_bg: Color::Rgb(30, 11, 66),
+            cpu_low: Color::Rgb(0, 255, 0),      // Green for low usage
+            cpu_medium: Color::Rgb(255, 165, 0),  // Orange for medium usage
+            cpu_high: Color::Rgb(255, 0, 0),      // Red for high usage
+        }
+    }
+}
+
+impl Theme {
+    pub const BUILTIN: [&'static str; 4] = ["cyberpunk", "matrix", "monochrome", "light"];
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "cyberpunk" => Some(Self::default()),
+            "matrix" => Some(Self {
+                neon_pink: Color::Rgb(0, 255, 65),
+                electric_blue: Color::Rgb(0, 190, 50),
+                cyber_yellow: Color::Rgb(180, 255, 180),
+                neon_green: Color::Rgb(0, 230, 60),
+                dark_bg: Color::Rgb(0, 10, 0),
+                light_bg: Color::Rgb(0, 40, 10),
+                cpu_low: Color::Rgb(0, 200, 50),
+                cpu_medium: Color::Rgb(200, 255, 0),
+                cpu_high: Color::Rgb(255, 80, 80),
+            }),
+            // Only the terminal's own colors, relying on bold and reverse video for emphasis
+            "monochrome" => Some(Self {
+                neon_pink: Color::White,
+                electric_blue: Color::Gray,
+                cyber_yellow: Color::White,
+                neon_green: Color::Reset,
+                dark_bg: Color::Reset,
+                light_bg: Color::Reset,
+                cpu_low: Color::Gray,
+                cpu_medium: Color::White,
+                cpu_high: Color::White,
+            }),
+            "light" => Some(Self {
+                neon_pink: Color::Rgb(170, 0, 100),
+                electric_blue: Color::Rgb(0, 90, 160),
+                cyber_yellow: Color::Rgb(150, 90, 0),
+                neon_green: Color::Rgb(20, 20, 20),
+                dark_bg: Color::Rgb(235, 235, 225),
+                light_bg: Color::Rgb(215, 215, 205),
+                cpu_low: Color::Rgb(0, 130, 40),
+                cpu_medium: Color::Rgb(200, 110, 0),
+                cpu_high: Color::Rgb(200, 0, 0),
+            }),
+            _ => None,
+        }
+    }
+
+    // A built-in theme or `<name>.toml` from the user theme directory
+    pub fn load(name: &str, dir: Option<&Path>) -> Result<Self> {
+        if let Some(theme) = Self::builtin(name) {
+            return Ok(theme);
+        }
+        let path = dir
+            .map(|dir| dir.join(format!("{}.toml", name)))
+            .filter(|path| path.is_file())
+            .ok_or_else(|| anyhow!("unknown theme '{}' (available: {})", name, names(dir).join(", ")))?;
+        let text = fs::read_to_string(&path).with_context(|| format!("cannot read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("theme {}", path.display()))
+    }
+
+    // Colors are names, "#rrggbb" or 256-color indexes; missing ones come from `base`
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut table: Table = text.parse().map_err(|err: toml::de::Error| anyhow!("{}", err.message().trim()))?;
+        let mut theme = match table.remove("base") {
+            Some(base) => {
+                let base = base.as_str().ok_or_else(|| anyhow!("base: expected a theme name"))?;
+                Self::builtin(base).ok_or_else(|| anyhow!("base: unknown built-in theme '{}'", base))?
+            }
+            None => Self::default(),
+        };
+
+        for (key, value) in table {
+            let slot = match key.as_str() {
+                "neon_pink" => &mut theme.neon_pink,
+                "electric_blue" => &mut theme.electric_blue,
+                "cyber_yellow" => &mut theme.cyber_yellow,
+                "neon_green" => &mut theme.neon_green,
+                "dark_bg" => &mut theme.dark_bg,
+                "light_bg" => &mut theme.light_bg,
+                "cpu_low" => &mut theme.cpu_low,
+                "cpu_medium" => &mut theme.cpu_medium,
+                "cpu_high" => &mut theme.cpu_high,
+                _ => bail!("{}: unknown color", key),
+            };
+            *slot = value
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| value.as_integer().map(|index| index.to_string()))
+                .and_then(|color| Color::from_str(&color).ok())
+                .ok_or_else(|| anyhow!("{}: invalid color {}", key, value))?;
+        }
+        Ok(theme)
+    }
+
+    pub fn downgrade(self, depth: ColorDepth) -> Self {
+        let convert = |color| depth.convert(color);
+        Self {
+            neon_pink: convert(self.neon_pink),
+            electric_blue: convert(self.electric_blue),
+            cyber_yellow: convert(self.cyber_yellow),
+            neon_green: convert(self.neon_green),
+            dark_bg: convert(self.dark_bg),
+            light_bg: convert(self.light_bg),
+            cpu_low: convert(self.cpu_low),
+            cpu_medium: convert(self.cpu_medium),
+            cpu_high: convert(self.cpu_high),
+        }
+    }
+}
+
+// Built-in themes followed by the user's theme files, sorted by name
+pub fn names(dir: Option<&Path>) -> Vec<String> {
+    let mut names: Vec<String> = Theme::BUILTIN.iter().map(|name| name.to_string()).collect();
+    let mut custom: Vec<String> = dir
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let path: PathBuf = entry.ok()?.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            (!names.contains(&name)).then_some(name)
+        })
+        .collect();
+    custom.sort();
+    names.extend(custom);
+    names
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ColorDepth {
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+// Approximate RGB values of the 16 ANSI colors, as used by xterm
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    // NO_COLOR (https://no-color.org) wins, then COLORTERM and TERM
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        if !var("NO_COLOR").is_empty() {
+            return ColorDepth::None;
+        }
+        let colorterm = var("COLORTERM");
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+        let term = var("TERM");
+        if term == "dumb" {
+            ColorDepth::None
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else if cfg!(windows) && term.is_empty() {
+            // Windows Terminal and modern consoles render RGB without advertising it
+            ColorDepth::TrueColor
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    pub fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (_, Color::Reset) | (ColorDepth::TrueColor, _) => color,
+            (ColorDepth::None, _) => Color::Reset,
+            (ColorDepth::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorDepth::Ansi16, Color::Rgb(r, g, b)) => nearest_16(r, g, b),
+            (ColorDepth::Ansi16, Color::Indexed(index)) if index >= 16 => {
+                let (r, g, b) = indexed_rgb(index);
+                nearest_16(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI.iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+// Closest entry of the 6x6x6 color cube or the 24-step gray ramp
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - value as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray = if average < 8 { 0 } else { ((average - 8) / 10).min(23) as u8 };
+    let gray_index = 232 + gray;
+
+    if distance(indexed_rgb(gray_index), (r, g, b)) < distance(indexed_rgb(cube), (r, g, b)) {
+        gray_index
+    } else {
+        cube
+    }
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize].1,
+        16..=231 => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}